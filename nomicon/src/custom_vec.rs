@@ -1,60 +1,141 @@
 // https://doc.rust-lang.org/nomicon/vec.html
 
-use std::alloc::{handle_alloc_error, AllocRef, Global, Layout};
+use std::alloc::{self, handle_alloc_error, Layout};
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
 use std::marker::PhantomData;
 use std::mem;
-use std::ops::{Deref, DerefMut};
-use std::ptr::{self, NonNull, Unique};
+use std::ops::{Bound, Deref, DerefMut, Index, IndexMut, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::slice::{self, SliceIndex};
 
-struct RawVec<T> {
-    ptr: Unique<T>,
-    cap: usize,
+pub(crate) struct RawVec<T> {
+    pub(crate) ptr: NonNull<T>,
+    pub(crate) cap: usize,
+    // we own Ts, NonNull alone doesn't say so.
+    _marker: PhantomData<T>,
 }
 
+// NonNull is neither Send nor Sync, but we own the buffer, just like a Box<[T]> would.
+unsafe impl<T: Send> Send for RawVec<T> {}
+unsafe impl<T: Sync> Sync for RawVec<T> {}
+
 impl<T> RawVec<T> {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         // !0 is usize::MAX
         let cap = if mem::size_of::<T>() == 0 { !0 } else { 0 };
         RawVec {
-            ptr: Unique::empty(),
+            // dangling, but well-aligned, which is all that empty slices and ZSTs need.
+            ptr: NonNull::dangling(),
             cap,
+            _marker: PhantomData,
         }
     }
 
-    fn grow(&mut self) {
-        unsafe {
-            let elem_size = mem::size_of::<T>();
+    pub(crate) fn with_capacity(cap: usize) -> Self {
+        let mut buf = RawVec::new();
+        buf.reserve_exact(0, cap);
+        buf
+    }
+
+    // makes room for `additional` more elements after the first `len`,
+    // growing at least geometrically so that repeated pushes are amortized O(1).
+    pub(crate) fn try_reserve(
+        &mut self,
+        len: usize,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        if additional <= self.cap.wrapping_sub(len) {
+            return Ok(());
+        }
+        // since we set the capacity to usize::MAX when elem_size is
+        // 0, getting to here necessarily means the Vec is overfull.
+        let required = len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        // tiny allocations aren't worth it, the allocator rounds them up anyway.
+        let min_cap = if mem::size_of::<T>() == 1 { 8 } else { 4 };
+        let new_cap = required.max(self.cap.saturating_mul(2)).max(min_cap);
+        self.set_capacity(new_cap)
+    }
+
+    // like try_reserve, but without room for future growth.
+    pub(crate) fn try_reserve_exact(
+        &mut self,
+        len: usize,
+        additional: usize,
+    ) -> Result<(), TryReserveError> {
+        if additional <= self.cap.wrapping_sub(len) {
+            return Ok(());
+        }
+        let required = len
+            .checked_add(additional)
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        self.set_capacity(required)
+    }
+
+    pub(crate) fn reserve(&mut self, len: usize, additional: usize) {
+        if let Err(e) = self.try_reserve(len, additional) {
+            e.handle()
+        }
+    }
 
-            // since we set the capacity to usize::MAX when elem_size is
-            // 0, getting to here necessarily means the Vec is overfull.
-            assert!(elem_size != 0, "capacity overflow");
+    fn reserve_exact(&mut self, len: usize, additional: usize) {
+        if let Err(e) = self.try_reserve_exact(len, additional) {
+            e.handle()
+        }
+    }
 
-            let (new_cap, ptr) = if self.cap == 0 {
-                let ptr = Global.alloc(Layout::array::<T>(1).unwrap());
-                (1, ptr)
+    // shrinks the buffer to `cap` elements, which must be at most the current capacity.
+    pub(crate) fn shrink_to(&mut self, cap: usize) {
+        assert!(cap <= self.cap, "tried to shrink to a larger capacity");
+        if mem::size_of::<T>() == 0 || cap == self.cap {
+            return;
+        }
+        if cap == 0 {
+            // SAFETY: the buffer was allocated with this layout, and nothing is left in it.
+            unsafe { alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout()) };
+            self.ptr = NonNull::dangling();
+            self.cap = 0;
+        } else if let Err(e) = self.set_capacity(cap) {
+            e.handle()
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        // this was checked when the buffer was allocated.
+        Layout::array::<T>(self.cap).unwrap()
+    }
+
+    // (re)allocates the buffer for `new_cap` elements, which must be non-zero.
+    fn set_capacity(&mut self, new_cap: usize) -> Result<(), TryReserveError> {
+        // Layout::array fails if the size overflows isize::MAX, which is also
+        // the most that pointer offsets can handle.
+        let new_layout =
+            Layout::array::<T>(new_cap).map_err(|_| TryReserveError::CapacityOverflow)?;
+
+        // SAFETY: the new layout isn't zero-sized, and an existing buffer was allocated
+        // with the old layout.
+        let ptr = unsafe {
+            if self.cap == 0 {
+                alloc::alloc(new_layout)
             } else {
-                let new_cap = 2 * self.cap;
-                let c: NonNull<T> = self.ptr.into();
-                let ptr = Global.realloc(
-                    c.cast(),
-                    Layout::array::<T>(self.cap).unwrap(),
-                    Layout::array::<T>(new_cap).unwrap().size(),
-                );
-                (new_cap, ptr)
-            };
-
-            // If allocate or reallocate fail, oom
-            if ptr.is_err() {
-                handle_alloc_error(Layout::from_size_align_unchecked(
-                    new_cap * elem_size,
-                    mem::align_of::<T>(),
-                ))
+                alloc::realloc(
+                    self.ptr.as_ptr() as *mut u8,
+                    self.layout(),
+                    new_layout.size(),
+                )
             }
-            let ptr = ptr.unwrap().0; // unpack tuple !?
+        };
 
-            self.ptr = Unique::new_unchecked(ptr.as_ptr() as *mut _);
-            self.cap = new_cap;
-        }
+        // on failure, the old buffer is still there, and still ours.
+        self.ptr = NonNull::new(ptr as *mut T)
+            .ok_or(TryReserveError::AllocError { layout: new_layout })?;
+        self.cap = new_cap;
+        Ok(())
     }
 }
 
@@ -63,16 +144,47 @@ impl<T> Drop for RawVec<T> {
         let elem_size = mem::size_of::<T>();
         if self.cap != 0 && elem_size != 0 {
             unsafe {
-                let c: NonNull<T> = self.ptr.into();
-                Global.dealloc(c.cast(), Layout::array::<T>(self.cap).unwrap());
+                alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout());
+            }
+        }
+    }
+}
+
+/// The error returned by [`Vec::try_reserve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryReserveError {
+    /// The capacity would exceed `isize::MAX` bytes.
+    CapacityOverflow,
+    /// The allocator failed to provide the memory.
+    AllocError { layout: Layout },
+}
+
+impl TryReserveError {
+    // what the infallible methods do on failure.
+    pub(crate) fn handle(self) -> ! {
+        match self {
+            TryReserveError::CapacityOverflow => panic!("capacity overflow"),
+            TryReserveError::AllocError { layout } => handle_alloc_error(layout),
+        }
+    }
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => f.write_str("capacity overflow"),
+            TryReserveError::AllocError { layout } => {
+                write!(f, "failed to allocate {} bytes", layout.size())
             }
         }
     }
 }
 
+impl std::error::Error for TryReserveError {}
+
 pub struct Vec<T> {
-    buf: RawVec<T>,
-    len: usize,
+    pub(crate) buf: RawVec<T>,
+    pub(crate) len: usize,
 }
 
 impl<T> Vec<T> {
@@ -91,9 +203,88 @@ impl<T> Vec<T> {
         }
     }
 
+    pub fn with_capacity(capacity: usize) -> Self {
+        Vec {
+            buf: RawVec::with_capacity(capacity),
+            len: 0,
+        }
+    }
+
+    /// Makes room for at least `additional` more elements, leaving room for further growth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(self.len, additional);
+    }
+
+    /// Makes room for exactly `additional` more elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.buf.reserve_exact(self.len, additional);
+    }
+
+    /// Like [`Vec::reserve`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.buf.try_reserve(self.len, additional)
+    }
+
+    /// Like [`Vec::reserve_exact`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.buf.try_reserve_exact(self.len, additional)
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.buf.shrink_to(self.len);
+    }
+
+    /// Shrinks the capacity to `min_capacity`, but not below the length.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        if min_capacity < self.cap() {
+            self.buf.shrink_to(self.len.max(min_capacity));
+        }
+    }
+
+    /// Drops all elements past the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        // SAFETY: len is in bounds, and the elements past it are initialized.
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { self.ptr().add(len) }, self.len - len);
+        // shorten first, so that a panicking destructor can't make us drop the tail twice.
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Truncates to `new_len`, or fills up to it with clones of `value`.
+    pub fn resize(&mut self, new_len: usize, value: T)
+    where
+        T: Clone,
+    {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return;
+        }
+        self.reserve(new_len - self.len);
+        while self.len + 1 < new_len {
+            self.push(value.clone());
+        }
+        // the last one doesn't need a clone.
+        self.push(value);
+    }
+
     pub fn push(&mut self, elem: T) {
         if self.len == self.cap() {
-            self.buf.grow();
+            self.buf.reserve(self.len, 1);
         }
 
         unsafe {
@@ -119,7 +310,7 @@ impl<T> Vec<T> {
     pub fn insert(&mut self, index: usize, elem: T) {
         assert!(index <= self.len, "index out of bounds");
         if self.len == self.cap() {
-            self.buf.grow();
+            self.buf.reserve(self.len, 1);
         }
 
         unsafe {
@@ -158,7 +349,7 @@ impl<T> Default for Vec<T> {
 
 impl<T> Drop for Vec<T> {
     fn drop(&mut self) {
-        while let Some(_) = self.pop() {}
+        while self.pop().is_some() {}
     }
 }
 
@@ -175,22 +366,185 @@ impl<T> DerefMut for Vec<T> {
     }
 }
 
-struct RawValIter<T> {
+impl<T: Clone> Clone for Vec<T> {
+    fn clone(&self) -> Self {
+        // if a clone panics, the ones made so far are dropped along with the new Vec.
+        let mut v = Vec::with_capacity(self.len);
+        for elem in self.iter() {
+            v.push(elem.clone());
+        }
+        v
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Vec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash> Hash for Vec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<T, I: SliceIndex<[T]>> Index<I> for Vec<T> {
+    type Output = I::Output;
+    fn index(&self, index: I) -> &I::Output {
+        &(**self)[index]
+    }
+}
+
+impl<T, I: SliceIndex<[T]>> IndexMut<I> for Vec<T> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut (**self)[index]
+    }
+}
+
+// compares against anything that looks like a list of elements, in both directions.
+macro_rules! impl_eq {
+    ($([$($generics:tt)*] $lhs:ty, $rhs:ty;)*) => {$(
+        impl<$($generics)* T, U> PartialEq<$rhs> for $lhs
+        where
+            T: PartialEq<U>,
+        {
+            fn eq(&self, other: &$rhs) -> bool {
+                self[..] == other[..]
+            }
+        }
+    )*};
+}
+
+impl_eq! {
+    [] Vec<T>, Vec<U>;
+    [] Vec<T>, [U];
+    ['a,] Vec<T>, &'a [U];
+    ['a,] Vec<T>, &'a mut [U];
+    [const N: usize,] Vec<T>, [U; N];
+    ['a, const N: usize,] Vec<T>, &'a [U; N];
+    [] Vec<T>, std::vec::Vec<U>;
+    [] [T], Vec<U>;
+    ['a,] &'a [T], Vec<U>;
+    ['a,] &'a mut [T], Vec<U>;
+    [const N: usize,] [T; N], Vec<U>;
+    [] std::vec::Vec<T>, Vec<U>;
+}
+
+impl<T: Eq> Eq for Vec<T> {}
+
+impl<T: PartialOrd> PartialOrd for Vec<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: Ord> Ord for Vec<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T> AsRef<[T]> for Vec<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> AsMut<[T]> for Vec<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T> Borrow<[T]> for Vec<T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> BorrowMut<[T]> for Vec<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: Clone> From<&[T]> for Vec<T> {
+    fn from(slice: &[T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T: Clone> From<&mut [T]> for Vec<T> {
+    fn from(slice: &mut [T]) -> Self {
+        Vec::from(&*slice)
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Vec<T> {
+    fn from(array: [T; N]) -> Self {
+        let mut v = Vec::with_capacity(N);
+        let array = mem::ManuallyDrop::new(array);
+        // SAFETY: the elements are moved into the new buffer, and the array is never dropped.
+        unsafe { ptr::copy_nonoverlapping(array.as_ptr(), v.ptr(), N) };
+        v.len = N;
+        v
+    }
+}
+
+/// Takes over the buffer, without copying the elements.
+impl<T> From<std::vec::Vec<T>> for Vec<T> {
+    fn from(v: std::vec::Vec<T>) -> Self {
+        let mut v = mem::ManuallyDrop::new(v);
+        // for ZSTs, this is usize::MAX, just like ours.
+        let cap = v.capacity();
+        Vec {
+            buf: RawVec {
+                // SAFETY: a Vec's pointer is never null, and it allocates just like we do:
+                // with the global allocator and the layout of an array of `cap` Ts.
+                ptr: unsafe { NonNull::new_unchecked(v.as_mut_ptr()) },
+                cap,
+                _marker: PhantomData,
+            },
+            len: v.len(),
+        }
+    }
+}
+
+/// Hands over the buffer, without copying the elements.
+impl<T> From<Vec<T>> for std::vec::Vec<T> {
+    fn from(v: Vec<T>) -> Self {
+        let v = mem::ManuallyDrop::new(v);
+        // SAFETY: see From<std::vec::Vec<T>> for Vec<T>. for ZSTs, both use usize::MAX.
+        unsafe { std::vec::Vec::from_raw_parts(v.ptr(), v.len, v.cap()) }
+    }
+}
+
+impl<T> From<Vec<T>> for Box<[T]> {
+    fn from(v: Vec<T>) -> Self {
+        std::vec::Vec::from(v).into_boxed_slice()
+    }
+}
+
+pub(crate) struct RawValIter<T> {
     start: *const T,
     end: *const T,
 }
 
 impl<T> RawValIter<T> {
     // unsafe to construct because it has no associated lifetimes (required).
-    unsafe fn new(slice: &[T]) -> Self {
+    // `start` must come from the buffer itself rather than a shared slice of it,
+    // so that the elements can be dropped in place or overwritten.
+    pub(crate) unsafe fn from_raw_parts(start: *const T, len: usize) -> Self {
         RawValIter {
-            start: slice.as_ptr(),
+            start,
+            // for ZSTs, the pointers only count the elements, so step by single bytes.
             end: if mem::size_of::<T>() == 0 {
-                ((slice.as_ptr() as usize) + slice.len()) as *const _
-            } else if slice.len() == 0 {
-                slice.as_ptr()
+                start.wrapping_byte_add(len)
+            } else if len == 0 {
+                start
             } else {
-                slice.as_ptr().add(slice.len())
+                start.add(len)
             },
         }
     }
@@ -203,13 +557,15 @@ impl<T> Iterator for RawValIter<T> {
             None
         } else {
             unsafe {
-                let result = ptr::read(self.start);
-                self.start = if mem::size_of::<T>() == 0 {
-                    (self.start as usize + 1) as *const _
+                if mem::size_of::<T>() == 0 {
+                    self.start = self.start.wrapping_byte_add(1);
+                    // the counting pointer might not be aligned, but any aligned one will do.
+                    Some(ptr::read(NonNull::<T>::dangling().as_ptr()))
                 } else {
-                    self.start.offset(1)
-                };
-                Some(result)
+                    let result = ptr::read(self.start);
+                    self.start = self.start.offset(1);
+                    Some(result)
+                }
             }
         }
     }
@@ -217,7 +573,7 @@ impl<T> Iterator for RawValIter<T> {
     fn size_hint(&self) -> (usize, Option<usize>) {
         let elem_size = mem::size_of::<T>();
         let len =
-            (self.end as usize - self.start as usize) / if elem_size == 0 { 1 } else { elem_size };
+            (self.end.addr() - self.start.addr()) / if elem_size == 0 { 1 } else { elem_size };
         (len, Some(len))
     }
 }
@@ -228,22 +584,73 @@ impl<T> DoubleEndedIterator for RawValIter<T> {
             None
         } else {
             unsafe {
-                self.end = if mem::size_of::<T>() == 0 {
-                    (self.end as usize - 1) as *const _
+                if mem::size_of::<T>() == 0 {
+                    self.end = self.end.wrapping_byte_sub(1);
+                    Some(ptr::read(NonNull::<T>::dangling().as_ptr()))
                 } else {
-                    self.end.offset(-1)
-                };
-                Some(ptr::read(self.end))
+                    self.end = self.end.offset(-1);
+                    Some(ptr::read(self.end))
+                }
             }
         }
     }
 }
 
 pub struct IntoIter<T> {
-    _buf: RawVec<T>,
+    buf: RawVec<T>,
     iter: RawValIter<T>,
 }
 
+impl<T> IntoIter<T> {
+    /// Maps the remaining elements with `f` and collects the results into a `Vec`.
+    ///
+    /// If `U` has the same size and alignment as `T`, the results are written into the buffer
+    /// of this iterator as the elements are taken out of it, instead of allocating a new one.
+    pub fn map_in_place<U, F: FnMut(T) -> U>(mut self, mut f: F) -> Vec<U> {
+        if mem::size_of::<U>() != mem::size_of::<T>()
+            || mem::align_of::<U>() != mem::align_of::<T>()
+        {
+            return self.map(f).collect();
+        }
+
+        // the results written so far. if f panics, these are dropped first,
+        // then the IntoIter drops the elements that haven't been read and frees the buffer.
+        struct Written<U> {
+            start: *mut U,
+            len: usize,
+        }
+
+        impl<U> Drop for Written<U> {
+            fn drop(&mut self) {
+                unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.start, self.len)) };
+            }
+        }
+
+        let mut written = Written {
+            start: self.buf.ptr.as_ptr() as *mut U,
+            len: 0,
+        };
+        for elem in self.iter.by_ref() {
+            let out = f(elem);
+            // SAFETY: we have read at least as many elements as we have written, so this slot
+            // has already been moved out of. U fits, since it has the same layout as T.
+            unsafe { ptr::write(written.start.add(written.len), out) };
+            written.len += 1;
+        }
+
+        let len = written.len;
+        mem::forget(written);
+        let buf = RawVec {
+            ptr: self.buf.ptr.cast(),
+            cap: self.buf.cap,
+            _marker: PhantomData,
+        };
+        // the iterator is empty, and its buffer now belongs to the new Vec.
+        mem::forget(self);
+        Vec { buf, len }
+    }
+}
+
 impl<T> Iterator for IntoIter<T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
@@ -260,6 +667,10 @@ impl<T> DoubleEndedIterator for IntoIter<T> {
     }
 }
 
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
 impl<T> Drop for IntoIter<T> {
     fn drop(&mut self) {
         // drop any remaining elements
@@ -267,25 +678,132 @@ impl<T> Drop for IntoIter<T> {
     }
 }
 
-impl<T> Vec<T> {
-    pub fn into_iter(self) -> IntoIter<T> {
+impl<T> IntoIterator for Vec<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
         unsafe {
-            let iter = RawValIter::new(&self);
+            let iter = RawValIter::from_raw_parts(self.ptr(), self.len);
 
             let buf = ptr::read(&self.buf);
             mem::forget(self);
 
-            IntoIter { iter, _buf: buf }
+            IntoIter { iter, buf }
         }
     }
 }
 
+impl<'a, T> IntoIterator for &'a Vec<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Vec<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T> FromIterator<T> for Vec<T> {
+    /// Use [`IntoIter::map_in_place`] to reuse the buffer of a `Vec` that is mapped and
+    /// collected again.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Vec::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<T> Extend<T> for Vec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        // the lower bound is all we can rely on, push grows as needed after that.
+        self.reserve(iter.size_hint().0);
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for Vec<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+// the start and end of `range` within a slice of length `len`.
+pub(crate) fn bounds(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end,
+        "range starts at {} but ends at {}",
+        start,
+        end
+    );
+    assert!(
+        end <= len,
+        "range end {} out of bounds for length {}",
+        end,
+        len
+    );
+    (start, end)
+}
+
+/// The elements removed by [`Vec::drain`].
+///
+/// The elements after the drained range are moved back in place when this is dropped.
 pub struct Drain<'a, T: 'a> {
-    vec: PhantomData<&'a mut Vec<T>>,
+    // pointers rather than references, since iter points into the same buffer.
+    // `len` is the length of the vector the buffer belongs to.
+    buf: *mut T,
+    len: NonNull<usize>,
+    // the elements after the range, which are put back once we're done.
+    tail_start: usize,
+    tail_len: usize,
     iter: RawValIter<T>,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+// like the &mut Vec<T> it stands for.
+unsafe impl<T: Send> Send for Drain<'_, T> {}
+unsafe impl<T: Sync> Sync for Drain<'_, T> {}
+
+impl<'a, T> Drain<'a, T> {
+    // drains [start, end) of the `*len` elements at `buf`.
+    //
+    // both pointers must stay valid and unused by anyone else for 'a, and `end` must be
+    // at most `*len`. this is shared by all vectors, whatever their buffer looks like.
+    pub(crate) unsafe fn new(buf: *mut T, len: NonNull<usize>, start: usize, end: usize) -> Self {
+        let iter = RawValIter::from_raw_parts(buf.add(start), end - start);
+        // from here on, only the head is part of the vector. if the drain gets leaked,
+        // so does the tail, but nothing is dropped twice.
+        let tail_len = *len.as_ptr() - end;
+        *len.as_ptr() = start;
+        Drain {
+            buf,
+            len,
+            tail_start: end,
+            tail_len,
+            iter,
+            _marker: PhantomData,
+        }
+    }
 }
 
-// delete
 impl<'a, T> Iterator for Drain<'a, T> {
     type Item = T;
     fn next(&mut self) -> Option<T> {
@@ -302,21 +820,800 @@ impl<'a, T> DoubleEndedIterator for Drain<'a, T> {
     }
 }
 
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
 impl<'a, T> Drop for Drain<'a, T> {
     fn drop(&mut self) {
-        for _ in &mut self.iter {}
+        // moves the tail back, even if dropping one of the remaining elements panics.
+        struct MoveTail<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for MoveTail<'_, '_, T> {
+            fn drop(&mut self) {
+                let drain = &mut *self.0;
+                unsafe {
+                    let len = drain.len.as_ptr();
+                    let start = *len;
+                    if drain.tail_start != start {
+                        ptr::copy(
+                            drain.buf.add(drain.tail_start),
+                            drain.buf.add(start),
+                            drain.tail_len,
+                        );
+                    }
+                    *len = start + drain.tail_len;
+                }
+            }
+        }
+
+        let guard = MoveTail(self);
+        let iter = &mut guard.0.iter;
+        let remaining = iter.size_hint().0;
+        let first = if mem::size_of::<T>() == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            iter.start as *mut T
+        };
+        // empty the iterator first, so that nothing is dropped twice if a destructor panics.
+        iter.start = iter.end;
+        // SAFETY: these are the elements that weren't yielded. drop_in_place keeps dropping
+        // the rest of them if one of them panics.
+        unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(first, remaining)) };
+    }
+}
+
+/// The elements removed by [`Vec::splice`].
+///
+/// The replacement is put in when this is dropped.
+pub struct Splice<'a, I: Iterator + 'a> {
+    drain: Drain<'a, I::Item>,
+    // the vector being drained, and how to make room in it: `reserve(owner, used, additional)`
+    // makes room for `additional` elements after the first `used`, and returns the buffer.
+    owner: NonNull<()>,
+    reserve: unsafe fn(NonNull<()>, usize, usize) -> *mut I::Item,
+    replace_with: I,
+}
+
+// owner is only used for the drained vector, see Drain.
+unsafe impl<I: Iterator + Send> Send for Splice<'_, I> where I::Item: Send {}
+unsafe impl<I: Iterator + Sync> Sync for Splice<'_, I> where I::Item: Sync {}
+
+impl<'a, I: Iterator> Splice<'a, I> {
+    // `drain` and `owner` must come from the same pointer, so that using one doesn't
+    // invalidate the other.
+    pub(crate) unsafe fn new(
+        drain: Drain<'a, I::Item>,
+        owner: NonNull<()>,
+        reserve: unsafe fn(NonNull<()>, usize, usize) -> *mut I::Item,
+        replace_with: I,
+    ) -> Self {
+        Splice {
+            drain,
+            owner,
+            reserve,
+            replace_with,
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for Splice<'_, I> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<I::Item> {
+        self.drain.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
+    }
+}
+
+impl<I: Iterator> DoubleEndedIterator for Splice<'_, I> {
+    fn next_back(&mut self) -> Option<I::Item> {
+        self.drain.next_back()
+    }
+}
+
+impl<I: Iterator> Drop for Splice<'_, I> {
+    fn drop(&mut self) {
+        self.drain.by_ref().for_each(drop);
+
+        // collected first, since we need to know how much room to make. if this panics,
+        // dropping the drain still moves the tail back.
+        let mut replacement = Vec::new();
+        for elem in self.replace_with.by_ref() {
+            replacement.push(elem);
+        }
+
+        let drain = &mut self.drain;
+        unsafe {
+            let len = drain.len.as_ptr();
+            let start = *len;
+            let used = drain.tail_start + drain.tail_len;
+            let needed = start + replacement.len() + drain.tail_len;
+            // the buffer may move, even if it doesn't grow.
+            drain.buf = (self.reserve)(self.owner, used, needed.saturating_sub(used));
+            // move the tail out of the way, and put the replacement in front of it.
+            let new_tail_start = start + replacement.len();
+            ptr::copy(
+                drain.buf.add(drain.tail_start),
+                drain.buf.add(new_tail_start),
+                drain.tail_len,
+            );
+            drain.tail_start = new_tail_start;
+            ptr::copy_nonoverlapping(replacement.ptr(), drain.buf.add(start), replacement.len());
+            // the elements now belong to the vector, replacement only frees its buffer.
+            replacement.len = 0;
+            *len = new_tail_start;
+        }
+        // dropping the drain attaches the tail, which is already in place.
+    }
+}
+
+/// The elements removed by [`Vec::extract_if`].
+///
+/// Elements that haven't been looked at when this is dropped stay in the vector.
+pub struct ExtractIf<'a, T, F: FnMut(&mut T) -> bool> {
+    // see Drain.
+    buf: *mut T,
+    len: NonNull<usize>,
+    // the next element to look at.
+    idx: usize,
+    // the end of the range to look at.
+    end: usize,
+    // the number of elements removed so far, i.e. the size of the gap before idx.
+    del: usize,
+    old_len: usize,
+    pred: F,
+    _marker: PhantomData<&'a mut [T]>,
+}
+
+// see Drain.
+unsafe impl<T: Send, F: FnMut(&mut T) -> bool + Send> Send for ExtractIf<'_, T, F> {}
+unsafe impl<T: Sync, F: FnMut(&mut T) -> bool + Sync> Sync for ExtractIf<'_, T, F> {}
+
+impl<'a, T, F: FnMut(&mut T) -> bool> ExtractIf<'a, T, F> {
+    // with the same requirements as Drain::new.
+    pub(crate) unsafe fn new(
+        buf: *mut T,
+        len: NonNull<usize>,
+        start: usize,
+        end: usize,
+        pred: F,
+    ) -> Self {
+        let old_len = *len.as_ptr();
+        // there may be a gap in the middle while we're going, so pretend to be empty
+        // until we're dropped, in case we're leaked.
+        *len.as_ptr() = 0;
+        ExtractIf {
+            buf,
+            len,
+            idx: start,
+            end,
+            del: 0,
+            old_len,
+            pred,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Iterator for ExtractIf<'_, T, F> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        unsafe {
+            while self.idx < self.end {
+                let cur = self.buf.add(self.idx);
+                // if this panics, idx still points at cur, and drop closes the gap before it.
+                let extract = (self.pred)(&mut *cur);
+                self.idx += 1;
+                if extract {
+                    self.del += 1;
+                    return Some(ptr::read(cur));
+                } else if self.del > 0 {
+                    ptr::copy_nonoverlapping(cur, cur.sub(self.del), 1);
+                }
+            }
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.end - self.idx))
+    }
+}
+
+impl<T, F: FnMut(&mut T) -> bool> Drop for ExtractIf<'_, T, F> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.del > 0 {
+                ptr::copy(
+                    self.buf.add(self.idx),
+                    self.buf.add(self.idx - self.del),
+                    self.old_len - self.idx,
+                );
+            }
+            *self.len.as_ptr() = self.old_len - self.del;
+        }
+    }
+}
+
+// removes consecutive elements of the `*len` at `buf` for which `same(elem, previous)`
+// returns true, keeping the first of each run.
+pub(crate) unsafe fn dedup_by<T, F>(buf: *mut T, len: &mut usize, mut same: F)
+where
+    F: FnMut(&mut T, &mut T) -> bool,
+{
+    // [0, write) are the elements we keep, [read, old_len) the ones we haven't looked at yet.
+    // if `same` or a destructor panics, the guard closes the gap in between.
+    struct Guard<'a, T> {
+        buf: *mut T,
+        len: &'a mut usize,
+        read: usize,
+        write: usize,
+        old_len: usize,
+    }
+
+    impl<T> Drop for Guard<'_, T> {
+        fn drop(&mut self) {
+            unsafe {
+                let rest = self.old_len - self.read;
+                ptr::copy(self.buf.add(self.read), self.buf.add(self.write), rest);
+                *self.len = self.write + rest;
+            }
+        }
+    }
+
+    let old_len = *len;
+    if old_len <= 1 {
+        return;
+    }
+    *len = 0;
+    let mut guard = Guard {
+        buf,
+        len,
+        read: 1,
+        write: 1,
+        old_len,
+    };
+    while guard.read < old_len {
+        let cur = buf.add(guard.read);
+        let prev = buf.add(guard.write - 1);
+        if same(&mut *cur, &mut *prev) {
+            guard.read += 1;
+            ptr::drop_in_place(cur);
+        } else {
+            if guard.read != guard.write {
+                ptr::copy_nonoverlapping(cur, buf.add(guard.write), 1);
+            }
+            guard.read += 1;
+            guard.write += 1;
+        }
     }
 }
 
 impl<T> Vec<T> {
-    pub fn drain(&mut self) -> Drain<T> {
+    /// Removes the elements in `range`, and yields them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let (start, end) = bounds(range, self.len);
+        // SAFETY: the drain borrows the vector, and the buffer stays put while it does.
+        unsafe { Drain::new(self.ptr(), NonNull::from(&mut self.len), start, end) }
+    }
+
+    /// Replaces the elements in `range` with `replace_with`, and yields the removed ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Splice<'_, I::IntoIter>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+    {
+        unsafe fn reserve<T>(owner: NonNull<()>, used: usize, additional: usize) -> *mut T {
+            // only touches the buffer, not the length that the drain points to.
+            let buf = &mut (*owner.cast::<Vec<T>>().as_ptr()).buf;
+            buf.reserve(used, additional);
+            buf.ptr.as_ptr()
+        }
+
+        let (start, end) = bounds(range, self.len);
+        let owner = NonNull::from(self);
+        // SAFETY: everything is derived from owner, which the splice borrows.
+        unsafe {
+            let vec = owner.as_ptr();
+            let len = NonNull::new_unchecked(ptr::addr_of_mut!((*vec).len));
+            let drain = Drain::new((*vec).buf.ptr.as_ptr(), len, start, end);
+            Splice::new(drain, owner.cast(), reserve::<T>, replace_with.into_iter())
+        }
+    }
+
+    /// Yields and removes the elements in `range` for which `pred` returns true.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn extract_if<R, F>(&mut self, range: R, pred: F) -> ExtractIf<'_, T, F>
+    where
+        R: RangeBounds<usize>,
+        F: FnMut(&mut T) -> bool,
+    {
+        let (start, end) = bounds(range, self.len);
+        // SAFETY: see drain.
+        unsafe { ExtractIf::new(self.ptr(), NonNull::from(&mut self.len), start, end, pred) }
+    }
+
+    /// Keeps only the elements for which `f` returns true.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    /// Keeps only the elements for which `f` returns true.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        // everything that is left, extract_if drops right away.
+        self.extract_if(.., |elem| !f(elem)).for_each(drop);
+    }
+
+    /// Removes consecutive elements with the same key, keeping the first of each run.
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same(elem, previous)` returns true.
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, same: F) {
+        // SAFETY: the first len elements of the buffer are initialized.
+        unsafe { dedup_by(self.ptr(), &mut self.len, same) }
+    }
+
+    /// Splits off the elements from `at` on into a new vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is out of bounds.
+    pub fn split_off(&mut self, at: usize) -> Vec<T> {
+        assert!(at <= self.len, "index out of bounds");
+        let mut other = Vec::with_capacity(self.len - at);
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr().add(at), other.ptr(), self.len - at);
+            other.len = self.len - at;
+        }
+        self.len = at;
+        other
+    }
+
+    /// Moves all elements of `other` to the end of this vector.
+    pub fn append(&mut self, other: &mut Vec<T>) {
+        self.reserve(other.len);
+        unsafe {
+            ptr::copy_nonoverlapping(other.ptr(), self.ptr().add(self.len), other.len);
+        }
+        self.len += other.len;
+        other.len = 0;
+    }
+
+    /// Removes an element and replaces it with the last one, in O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
         unsafe {
-            let iter = RawValIter::new(&self);
-            self.len = 0;
-            Drain {
-                iter,
-                vec: PhantomData,
+            self.len -= 1;
+            let result = ptr::read(self.ptr().add(index));
+            ptr::copy(self.ptr().add(self.len), self.ptr().add(index), 1);
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracked::Tracked;
+    use std::rc::Rc;
+
+    #[test]
+    fn push_pop_insert_remove() {
+        let mut v = Vec::new();
+        for i in 0..100 {
+            v.push(i);
+        }
+        assert_eq!(v.len(), 100);
+        assert!(v.cap() >= 100);
+        assert_eq!(v.pop(), Some(99));
+        v.insert(0, -1);
+        v.insert(100, 100);
+        assert_eq!(v.remove(1), 0);
+        assert_eq!(&v[..3], &[-1, 1, 2]);
+        assert_eq!(v[v.len() - 1], 100);
+        v[0] = 7;
+        assert_eq!(v.iter().filter(|&&x| x == 7).count(), 2);
+    }
+
+    #[test]
+    fn drops_every_element_once() {
+        let rc = Rc::new(());
+        let mut v = Vec::new();
+        for _ in 0..10 {
+            v.push(Rc::clone(&rc));
+        }
+        drop(v.remove(3));
+        assert_eq!(Rc::strong_count(&rc), 10);
+
+        let mut iter = v.into_iter();
+        drop(iter.next());
+        drop(iter.next_back());
+        assert_eq!(iter.size_hint(), (7, Some(7)));
+        drop(iter);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let mut v = Vec::new();
+        v.push(Rc::clone(&rc));
+        v.push(Rc::clone(&rc));
+        assert_eq!(v.drain(..).count(), 2);
+        assert!(v.is_empty());
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        let mut v = Vec::new();
+        assert_eq!(v.cap(), usize::MAX);
+        for _ in 0..1000 {
+            v.push(());
+        }
+        v.insert(10, ());
+        assert_eq!(v.remove(0), ());
+        assert_eq!(v.len(), 1000);
+        assert_eq!(v.into_iter().rev().count(), 1000);
+
+        // over-aligned ZSTs have to be read from aligned pointers.
+        let mut v: Vec<[u64; 0]> = Vec::new();
+        v.push([]);
+        v.push([]);
+        let mut drain = v.drain(..);
+        assert_eq!(drain.size_hint(), (2, Some(2)));
+        assert_eq!(drain.next(), Some([]));
+        assert_eq!(drain.next_back(), Some([]));
+        assert_eq!(drain.next(), None);
+    }
+
+    #[test]
+    fn capacity_management() {
+        let mut v = Vec::with_capacity(10);
+        assert_eq!(v.cap(), 10);
+        let ptr = v.ptr();
+        for i in 0..10 {
+            v.push(i);
+        }
+        assert_eq!(v.ptr(), ptr);
+
+        v.push(10);
+        assert_eq!(v.cap(), 20);
+        v.reserve(5);
+        assert_eq!(v.cap(), 20);
+        v.reserve(10);
+        assert_eq!(v.cap(), 40);
+        v.reserve_exact(30);
+        assert_eq!(v.cap(), 41);
+
+        v.shrink_to(30);
+        assert_eq!(v.cap(), 30);
+        v.shrink_to(0);
+        assert_eq!(v.cap(), 11);
+        v.truncate(3);
+        v.shrink_to_fit();
+        assert_eq!((&*v, v.cap()), (&[0, 1, 2][..], 3));
+        v.clear();
+        v.shrink_to_fit();
+        assert_eq!(v.cap(), 0);
+        v.push(1);
+        assert_eq!(&*v, &[1]);
+    }
+
+    #[test]
+    fn truncate_drops_the_tail() {
+        let rc = Rc::new(());
+        let mut v = Vec::new();
+        for _ in 0..5 {
+            v.push(Rc::clone(&rc));
+        }
+        v.truncate(2);
+        assert_eq!(Rc::strong_count(&rc), 3);
+        v.clear();
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn try_reserve_errors() {
+        let mut v: Vec<u64> = Vec::new();
+        v.push(1);
+        assert_eq!(
+            v.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(
+            v.try_reserve_exact(isize::MAX as usize / 8 + 1),
+            Err(TryReserveError::CapacityOverflow)
+        );
+        assert_eq!(v.try_reserve(100), Ok(()));
+        assert_eq!(&*v, &[1]);
+
+        let mut zsts = Vec::new();
+        zsts.push(());
+        assert_eq!(zsts.try_reserve(usize::MAX - 1), Ok(()));
+        assert_eq!(
+            zsts.try_reserve(usize::MAX),
+            Err(TryReserveError::CapacityOverflow)
+        );
+    }
+
+    // miri reports huge allocations instead of failing them.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn try_reserve_alloc_failure() {
+        let mut v: Vec<u8> = Vec::new();
+        match v.try_reserve(isize::MAX as usize) {
+            Err(TryReserveError::AllocError { layout }) => {
+                assert_eq!(layout.size(), isize::MAX as usize)
             }
+            other => panic!("{:?}", other),
         }
+        v.push(1);
+        assert_eq!(&*v, &[1]);
+    }
+
+    fn tracked<'a>(live: &'a std::cell::Cell<usize>, values: &[i32]) -> Vec<Tracked<'a>> {
+        let mut v = Vec::new();
+        for &value in values {
+            v.push(Tracked::new(live, value));
+        }
+        v
+    }
+
+    fn values(v: &[Tracked<'_>]) -> std::vec::Vec<i32> {
+        v.iter().map(|t| t.value).collect()
+    }
+
+    #[test]
+    fn drain_ranges() {
+        let live = std::cell::Cell::new(0);
+        let mut v = tracked(&live, &[0, 1, 2, 3, 4, 5]);
+        let mut drain = v.drain(1..4);
+        assert_eq!(drain.next().map(|t| t.value), Some(1));
+        assert_eq!(drain.next_back().map(|t| t.value), Some(3));
+        drop(drain);
+        assert_eq!(values(&v), [0, 4, 5]);
+        assert_eq!(live.get(), 3);
+
+        // a leaked drain leaks the drained range and the tail,
+        // but leaves the vector in a valid state.
+        mem::forget(v.drain(1..=1));
+        assert_eq!(values(&v), [0]);
+        drop(v);
+        assert_eq!(live.get(), 2);
+    }
+
+    #[test]
+    fn drain_moves_tail_back_when_a_drop_panics() {
+        let live = std::cell::Cell::new(0);
+        let mut v = tracked(&live, &[0, -1, 2, 3]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(v.drain(..3));
+        }));
+        assert!(result.is_err());
+        assert_eq!(values(&v), [3]);
+        assert_eq!(live.get(), 1);
+    }
+
+    #[test]
+    fn splice() {
+        let mut v = Vec::new();
+        for i in 0..6 {
+            v.push(i);
+        }
+        let removed: std::vec::Vec<_> = v.splice(1..3, 10..14).collect();
+        assert_eq!(removed, [1, 2]);
+        assert_eq!(&*v, &[0, 10, 11, 12, 13, 3, 4, 5]);
+        v.splice(..5, Some(20));
+        assert_eq!(&*v, &[20, 3, 4, 5]);
+        v.splice(4.., 30..32);
+        assert_eq!(&*v, &[20, 3, 4, 5, 30, 31]);
+    }
+
+    #[test]
+    fn retain_and_extract_if() {
+        let live = std::cell::Cell::new(0);
+        let mut v = tracked(&live, &[1, 2, 3, 4, 5, 6]);
+        v.retain(|t| t.value % 2 == 0);
+        assert_eq!(values(&v), [2, 4, 6]);
+        assert_eq!(live.get(), 3);
+
+        let mut v = tracked(&live, &[1, 2, 3, 4, 5, 6]);
+        let odd: std::vec::Vec<_> = v.extract_if(1.., |t| t.value % 2 == 1).collect();
+        assert_eq!(values(&odd), [3, 5]);
+        assert_eq!(values(&v), [1, 2, 4, 6]);
+
+        // stopping early keeps what hasn't been looked at.
+        let mut extract = v.extract_if(.., |t| t.value > 1);
+        assert_eq!(extract.next().map(|t| t.value), Some(2));
+        drop(extract);
+        assert_eq!(values(&v), [1, 4, 6]);
+    }
+
+    #[test]
+    fn retain_panicking_predicate() {
+        let live = std::cell::Cell::new(0);
+        let mut v = tracked(&live, &[1, 2, 3, 4, 5]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.retain(|t| match t.value {
+                4 => panic!("predicate"),
+                x => x != 2,
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(values(&v), [1, 3, 4, 5]);
+        drop(v);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn dedup_split_append_swap_remove() {
+        let mut v = Vec::new();
+        for x in [1, 1, 2, 3, 3, 3, 1, 4, 4] {
+            v.push(x);
+        }
+        v.dedup_by_key(|x| *x);
+        assert_eq!(&*v, &[1, 2, 3, 1, 4]);
+        v.dedup_by_key(|x| *x / 2);
+        assert_eq!(&*v, &[1, 2, 1, 4]);
+
+        let mut tail = v.split_off(2);
+        assert_eq!((&*v, &*tail), (&[1, 2][..], &[1, 4][..]));
+        v.append(&mut tail);
+        assert!(tail.is_empty());
+        assert_eq!(v.swap_remove(0), 1);
+        assert_eq!(&*v, &[4, 2, 1]);
+        assert_eq!(v.swap_remove(2), 1);
+        assert_eq!(&*v, &[4, 2]);
+    }
+
+    #[test]
+    fn dedup_panicking_comparison() {
+        let live = std::cell::Cell::new(0);
+        let mut v = tracked(&live, &[1, 1, 2, 3, 3]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.dedup_by(|a, b| {
+                assert!(a.value != 3, "comparison");
+                a.value == b.value
+            });
+        }));
+        assert!(result.is_err());
+        assert_eq!(values(&v), [1, 2, 3, 3]);
+        drop(v);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn iterator_traits() {
+        let mut v: Vec<i32> = (0..5).collect();
+        for x in &mut v {
+            *x *= 2;
+        }
+        let mut sum = 0;
+        for x in &v {
+            sum += x;
+        }
+        assert_eq!(sum, 20);
+
+        v.extend(vec![10, 12]);
+        v.extend(&[14]);
+        let mut iter = v.into_iter();
+        assert_eq!(iter.len(), 8);
+        iter.next_back();
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.collect::<std::vec::Vec<_>>(), [0, 2, 4, 6, 8, 10, 12]);
+
+        let mut v: Vec<_> = (0..5).collect();
+        let mut drain = v.drain(1..4);
+        assert_eq!(drain.len(), 3);
+        drain.by_ref().for_each(drop);
+        assert_eq!((drain.next(), drain.next()), (None, None));
+    }
+
+    #[test]
+    fn map_in_place_reuses_the_buffer() {
+        let v: Vec<u32> = (0..10).collect();
+        let ptr = v.ptr() as usize;
+        let mut iter = v.into_iter();
+        iter.next();
+        let floats = iter.map_in_place(|x| x as f32 / 2.0);
+        assert_eq!(floats.ptr() as usize, ptr);
+        assert_eq!(floats.cap(), 10);
+        assert_eq!(&floats[..3], &[0.5, 1.0, 1.5]);
+
+        // different layouts get a new buffer.
+        let bytes = floats.into_iter().map_in_place(|x| x as u8);
+        assert_eq!(&*bytes, &[0, 1, 1, 2, 2, 3, 3, 4, 4]);
+
+        let units: Vec<()> = Vec::from_iter(vec![(), ()]);
+        assert_eq!(units.into_iter().map_in_place(|()| [0u8; 0]).len(), 2);
+    }
+
+    #[test]
+    fn map_in_place_panic() {
+        let live = std::cell::Cell::new(0);
+        let v = tracked(&live, &[0, 1, 2, 3]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            v.into_iter().map_in_place(|t| {
+                assert!(t.value != 2, "mapping");
+                Tracked::new(t.live, t.value + 10)
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn value_semantics() {
+        let v = Vec::from([3, 1, 2]);
+        assert_eq!(v, [3, 1, 2]);
+        assert_eq!(&v[..], v);
+        assert_eq!(v, std::vec![3, 1, 2]);
+        assert_eq!(v[1..], [1, 2]);
+        assert_eq!(format!("{:?}", v), "[3, 1, 2]");
+
+        let mut w = v.clone();
+        w[0] = 0;
+        assert!(w < v);
+        let set: std::collections::HashSet<_> =
+            IntoIterator::into_iter([v.clone(), w, v]).collect();
+        assert_eq!(set.len(), 2);
+
+        let std_vec: std::vec::Vec<i32> = Vec::from(&[1, 2][..]).into();
+        assert_eq!(std_vec, [1, 2]);
+        let ptr = std_vec.as_ptr();
+        let back = Vec::from(std_vec);
+        assert_eq!(back.ptr() as *const i32, ptr);
+        let boxed: Box<[()]> = Vec::from([(), ()]).into();
+        assert_eq!(boxed.len(), 2);
+    }
+
+    #[test]
+    fn clone_panic() {
+        struct Bomb<'a>(&'a std::cell::Cell<usize>, bool);
+        impl Clone for Bomb<'_> {
+            fn clone(&self) -> Self {
+                assert!(!self.1, "clone");
+                self.0.set(self.0.get() + 1);
+                Bomb(self.0, false)
+            }
+        }
+        impl Drop for Bomb<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() - 1);
+            }
+        }
+
+        let live = std::cell::Cell::new(3);
+        let v = Vec::from([Bomb(&live, false), Bomb(&live, false), Bomb(&live, true)]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| v.clone()));
+        assert!(result.is_err());
+        assert_eq!(live.get(), 3);
+    }
+
+    #[test]
+    fn avec_macro() {
+        let v: Vec<u32> = avec::avec![1, 2, 3];
+        assert_eq!(v.cap(), 3);
+        assert_eq!(v, [1, 2, 3]);
+        let v: Vec<String> = avec::avec![String::from("x"); 3];
+        assert_eq!(v, ["x", "x", "x"]);
+        let v: Vec<u8> = avec::avec![];
+        assert!(v.is_empty());
     }
 }
@@ -1 +1,8 @@
-mod custom_vec;
+#[macro_use]
+pub mod custom_vec;
+pub mod linked_list;
+pub mod small_vec;
+pub mod vec_deque;
+
+#[cfg(test)]
+mod tracked;
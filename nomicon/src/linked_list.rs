@@ -0,0 +1,837 @@
+// a doubly linked list, see https://rust-unofficial.github.io/too-many-lists/
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+// the first and last node of a chain, and its length.
+type Chain<T> = (NonNull<Node<T>>, NonNull<Node<T>>, usize);
+
+struct Node<T> {
+    prev: Link<T>,
+    next: Link<T>,
+    elem: T,
+}
+
+/// A doubly linked list.
+///
+/// Since it implements `Drop`, which may look at the elements, borrowed elements have to
+/// outlive the list:
+///
+/// ```compile_fail,E0597
+/// use nomicon::linked_list::LinkedList;
+///
+/// let mut list = LinkedList::new();
+/// let s = String::from("dropped before the list");
+/// list.push_back(&s);
+/// ```
+///
+/// Like a `Box`, it is only `UnwindSafe` if its elements are:
+///
+/// ```compile_fail,E0277
+/// use nomicon::linked_list::LinkedList;
+///
+/// fn unwind_safe<T: std::panic::UnwindSafe>(_: T) {}
+/// unwind_safe(LinkedList::<&mut i32>::new());
+/// ```
+pub struct LinkedList<T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    // NonNull doesn't own its pointee, this says that we own Nodes. without it, the auto
+    // traits would look at T as if it were behind a shared reference, e.g. the list would
+    // be UnwindSafe if T is only RefUnwindSafe.
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+// like a Box<Node<T>> for each element.
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
+// all node accesses go through raw places like (*node.as_ptr()).next, without making a
+// reference to the whole node, so that they don't invalidate the references to elements
+// that iter_mut and the cursor hand out.
+
+impl<T> LinkedList<T> {
+    pub fn new() -> Self {
+        LinkedList {
+            front: None,
+            back: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // links the nodes first..=last, which are linked to each other already, in between
+    // prev and next. None stands for the end of the list on either side.
+    unsafe fn link(
+        &mut self,
+        prev: Link<T>,
+        next: Link<T>,
+        first: NonNull<Node<T>>,
+        last: NonNull<Node<T>>,
+        count: usize,
+    ) {
+        (*first.as_ptr()).prev = prev;
+        (*last.as_ptr()).next = next;
+        match prev {
+            Some(prev) => (*prev.as_ptr()).next = Some(first),
+            None => self.front = Some(first),
+        }
+        match next {
+            Some(next) => (*next.as_ptr()).prev = Some(last),
+            None => self.back = Some(last),
+        }
+        self.len += count;
+    }
+
+    // unlinks a node of this list, and takes its element back.
+    unsafe fn unlink(&mut self, node: NonNull<Node<T>>) -> T {
+        let node = Box::from_raw(node.as_ptr());
+        match node.prev {
+            Some(prev) => (*prev.as_ptr()).next = node.next,
+            None => self.front = node.next,
+        }
+        match node.next {
+            Some(next) => (*next.as_ptr()).prev = node.prev,
+            None => self.back = node.prev,
+        }
+        self.len -= 1;
+        node.elem
+    }
+
+    // empties the list, and returns its nodes.
+    fn take_nodes(&mut self) -> Option<Chain<T>> {
+        let nodes = (self.front.take()?, self.back.take()?, self.len);
+        self.len = 0;
+        Some(nodes)
+    }
+
+    fn new_node(elem: T) -> NonNull<Node<T>> {
+        NonNull::from(Box::leak(Box::new(Node {
+            prev: None,
+            next: None,
+            elem,
+        })))
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        let node = Self::new_node(elem);
+        // SAFETY: the node is new, and front is ours.
+        unsafe { self.link(None, self.front, node, node, 1) };
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        let node = Self::new_node(elem);
+        unsafe { self.link(self.back, None, node, node, 1) };
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        // SAFETY: front is a node of this list.
+        self.front.map(|node| unsafe { self.unlink(node) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back.map(|node| unsafe { self.unlink(node) })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        unsafe { self.front.map(|node| &(*node.as_ptr()).elem) }
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.front.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        unsafe { self.back.map(|node| &(*node.as_ptr()).elem) }
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        unsafe { self.back.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn clear(&mut self) {
+        // dropped through the Drop impl, which keeps going if an element panics.
+        drop(mem::take(self));
+    }
+
+    /// Moves all elements of `other` to the end of this list, in O(1).
+    pub fn append(&mut self, other: &mut LinkedList<T>) {
+        if let Some((first, last, count)) = other.take_nodes() {
+            // SAFETY: the nodes were other's, and now they're ours.
+            unsafe { self.link(self.back, None, first, last, count) };
+        }
+    }
+
+    /// Splits off the elements from `at` on into a new list.
+    ///
+    /// Walks from whichever end of the list is closer to `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is out of bounds.
+    pub fn split_off(&mut self, at: usize) -> LinkedList<T> {
+        assert!(at <= self.len, "index out of bounds");
+        let len = self.len;
+        if at == 0 {
+            return mem::take(self);
+        }
+        // the split happens after the element at `at - 1`.
+        let mut cursor = if at - 1 < len / 2 {
+            let mut cursor = self.cursor_front_mut();
+            (0..at - 1).for_each(|_| cursor.move_next());
+            cursor
+        } else {
+            let mut cursor = self.cursor_back_mut();
+            (at..len).for_each(|_| cursor.move_prev());
+            cursor
+        };
+        cursor.split_after()
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            front: self.front,
+            back: self.back,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// A cursor at the first element, or at the ghost element if the list is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.front,
+            index: 0,
+            list: self,
+        }
+    }
+
+    /// A cursor at the last element, or at the ghost element if the list is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            cur: self.back,
+            index: self.len.saturating_sub(1),
+            list: self,
+        }
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        // keeps dropping the rest of the elements if one of them panics.
+        struct DropRest<'a, T>(&'a mut LinkedList<T>);
+
+        impl<T> Drop for DropRest<'_, T> {
+            fn drop(&mut self) {
+                while self.0.pop_front().is_some() {}
+            }
+        }
+
+        while let Some(elem) = self.pop_front() {
+            let guard = DropRest(self);
+            drop(elem);
+            mem::forget(guard);
+        }
+    }
+}
+
+impl<T: Clone> Clone for LinkedList<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for LinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for LinkedList<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for LinkedList<T> {}
+
+impl<T: PartialOrd> PartialOrd for LinkedList<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for LinkedList<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for LinkedList<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+        self.iter().for_each(|elem| elem.hash(state));
+    }
+}
+
+/// The elements of a [`LinkedList`], by reference.
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    // front and back don't tell us when they've met, this does.
+    len: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).next;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).prev;
+            &(*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter { ..*self }
+    }
+}
+
+/// The elements of a [`LinkedList`], by mutable reference.
+pub struct IterMut<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).next;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).prev;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// The elements of a [`LinkedList`], by value.
+pub struct IntoIter<T> {
+    list: LinkedList<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.list.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for LinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { list: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = LinkedList::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|elem| self.push_back(elem));
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+/// A cursor over a [`LinkedList`] that can edit it.
+///
+/// Besides the elements, the cursor can be at a "ghost" element between the back and the
+/// front of the list, so that the list wraps around, and that there is somewhere to be in
+/// an empty list.
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedList<T>,
+    // None at the ghost element.
+    cur: Link<T>,
+    // the index of cur. the ghost is at the length of the list.
+    index: usize,
+}
+
+// cur points into the list we borrow mutably.
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// The index of the current element, or `None` at the ghost element.
+    pub fn index(&self) -> Option<usize> {
+        self.cur.map(|_| self.index)
+    }
+
+    pub fn move_next(&mut self) {
+        match self.cur {
+            // SAFETY: cur is a node of the list, which we have borrowed.
+            Some(node) => unsafe {
+                self.cur = (*node.as_ptr()).next;
+                self.index += 1;
+            },
+            None => {
+                self.cur = self.list.front;
+                self.index = 0;
+            }
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        match self.cur {
+            Some(node) => unsafe {
+                self.cur = (*node.as_ptr()).prev;
+                self.index = self.index.checked_sub(1).unwrap_or(self.list.len);
+            },
+            None => {
+                self.cur = self.list.back;
+                self.index = self.list.len.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        unsafe { self.cur.map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    // the nodes around the current one, wrapping around through the ghost.
+    fn next_node(&self) -> Link<T> {
+        match self.cur {
+            Some(node) => unsafe { (*node.as_ptr()).next },
+            None => self.list.front,
+        }
+    }
+
+    fn prev_node(&self) -> Link<T> {
+        match self.cur {
+            Some(node) => unsafe { (*node.as_ptr()).prev },
+            None => self.list.back,
+        }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        unsafe { self.next_node().map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        unsafe { self.prev_node().map(|node| &mut (*node.as_ptr()).elem) }
+    }
+
+    /// Inserts an element after the current one, or at the front at the ghost element.
+    pub fn insert_after(&mut self, elem: T) {
+        let node = LinkedList::new_node(elem);
+        // SAFETY: the node is new, and cur and the one after it are nodes of the list.
+        unsafe { self.list.link(self.cur, self.next_node(), node, node, 1) };
+        if self.cur.is_none() {
+            // the ghost moved back.
+            self.index += 1;
+        }
+    }
+
+    /// Inserts an element before the current one, or at the back at the ghost element.
+    pub fn insert_before(&mut self, elem: T) {
+        let node = LinkedList::new_node(elem);
+        unsafe { self.list.link(self.prev_node(), self.cur, node, node, 1) };
+        self.index += 1;
+    }
+
+    /// Removes the current element, and moves to the next one.
+    ///
+    /// Does nothing at the ghost element.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.cur?;
+        self.cur = self.next_node();
+        // SAFETY: node is a node of the list, and we've moved off it.
+        Some(unsafe { self.list.unlink(node) })
+    }
+
+    /// Moves all elements of `other` in after the current one, in O(1).
+    pub fn splice_after(&mut self, mut other: LinkedList<T>) {
+        if let Some((first, last, count)) = other.take_nodes() {
+            // SAFETY: the nodes were other's, and now they're the list's.
+            unsafe {
+                self.list
+                    .link(self.cur, self.next_node(), first, last, count)
+            };
+            if self.cur.is_none() {
+                self.index += count;
+            }
+        }
+    }
+
+    /// Moves all elements of `other` in before the current one, in O(1).
+    pub fn splice_before(&mut self, mut other: LinkedList<T>) {
+        if let Some((first, last, count)) = other.take_nodes() {
+            unsafe {
+                self.list
+                    .link(self.prev_node(), self.cur, first, last, count)
+            };
+            self.index += count;
+        }
+    }
+
+    /// Splits off the elements after the current one into a new list, in O(1).
+    ///
+    /// At the ghost element, that is the whole list.
+    pub fn split_after(&mut self) -> LinkedList<T> {
+        let node = match self.cur {
+            Some(node) => node,
+            None => {
+                self.index = 0;
+                return mem::take(self.list);
+            }
+        };
+        let mut rest = LinkedList::new();
+        // SAFETY: node and the ones after it are nodes of the list.
+        unsafe {
+            if let Some(next) = (*node.as_ptr()).next.take() {
+                (*next.as_ptr()).prev = None;
+                rest.front = Some(next);
+                rest.back = self.list.back;
+                rest.len = self.list.len - self.index - 1;
+                self.list.back = Some(node);
+                self.list.len = self.index + 1;
+            }
+        }
+        rest
+    }
+
+    /// Splits off the elements before the current one into a new list, in O(1).
+    ///
+    /// At the ghost element, that is the whole list.
+    pub fn split_before(&mut self) -> LinkedList<T> {
+        let node = match self.cur {
+            Some(node) => node,
+            None => {
+                self.index = 0;
+                return mem::take(self.list);
+            }
+        };
+        let mut rest = LinkedList::new();
+        unsafe {
+            if let Some(prev) = (*node.as_ptr()).prev.take() {
+                (*prev.as_ptr()).next = None;
+                rest.front = self.list.front;
+                rest.back = Some(prev);
+                rest.len = self.index;
+                self.list.front = Some(node);
+                self.list.len -= self.index;
+                self.index = 0;
+            }
+        }
+        rest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracked::Tracked;
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    fn numbers(range: std::ops::Range<i32>) -> LinkedList<i32> {
+        range.collect()
+    }
+
+    fn contents(list: &LinkedList<i32>) -> Vec<i32> {
+        // checks the links in both directions.
+        let forward: Vec<_> = list.iter().copied().collect();
+        let mut backward: Vec<_> = list.iter().rev().copied().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward.len(), list.len());
+        forward
+    }
+
+    #[test]
+    fn push_pop_both_ends() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.pop_front(), None);
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+        *list.back_mut().unwrap() = 4;
+        assert_eq!(contents(&list), [1, 2, 4]);
+        assert_eq!(list.pop_back(), Some(4));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!((list.pop_back(), list.front(), list.len()), (None, None, 0));
+    }
+
+    #[test]
+    fn iterators() {
+        let mut list = numbers(0..5);
+        // every element is borrowed mutably at once, which miri checks.
+        let refs: Vec<&mut i32> = list.iter_mut().collect();
+        for r in refs {
+            *r *= 10;
+        }
+        let mut iter = list.iter();
+        assert_eq!(
+            (iter.next(), iter.next_back(), iter.len()),
+            (Some(&0), Some(&40), 3)
+        );
+        assert_eq!(iter.clone().count(), 3);
+        let mut iter = list.clone().into_iter();
+        assert_eq!((iter.next_back(), iter.next()), (Some(40), Some(0)));
+        assert_eq!(format!("{:?}", list), "[0, 10, 20, 30, 40]");
+    }
+
+    #[test]
+    fn append_and_split_off() {
+        let mut a = numbers(0..3);
+        let mut b = numbers(3..6);
+        a.append(&mut b);
+        assert!(b.is_empty());
+        b.append(&mut a);
+        assert_eq!(
+            (contents(&a), contents(&b)),
+            (vec![], vec![0, 1, 2, 3, 4, 5])
+        );
+
+        for at in 0..=6 {
+            let mut a = numbers(0..6);
+            let b = a.split_off(at);
+            assert_eq!(contents(&a), (0..at as i32).collect::<Vec<_>>());
+            assert_eq!(contents(&b), (at as i32..6).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn cursor_moves_through_the_ghost() {
+        let mut list = numbers(0..3);
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 0)));
+        cursor.move_prev();
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+        assert_eq!(cursor.peek_next(), Some(&mut 0));
+        assert_eq!(cursor.peek_prev(), Some(&mut 2));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(2));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+
+        let mut empty = LinkedList::<i32>::new();
+        let mut cursor = empty.cursor_back_mut();
+        cursor.move_next();
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+    }
+
+    #[test]
+    fn cursor_edits() {
+        let mut list = numbers(0..3);
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        cursor.insert_before(10);
+        cursor.insert_after(11);
+        assert_eq!((cursor.index(), cursor.current()), (Some(2), Some(&mut 1)));
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!((cursor.index(), cursor.current()), (Some(2), Some(&mut 11)));
+
+        cursor.splice_before(numbers(20..22));
+        cursor.splice_after(numbers(30..32));
+        assert_eq!(cursor.index(), Some(4));
+        assert_eq!(contents(&list), [0, 10, 20, 21, 11, 30, 31, 2]);
+
+        // at the ghost, inserting after means at the front, and before means at the back.
+        let mut cursor = list.cursor_back_mut();
+        cursor.move_next();
+        cursor.insert_after(-1);
+        cursor.insert_before(99);
+        cursor.splice_after(numbers(-3..-1));
+        assert_eq!(cursor.remove_current(), None);
+        cursor.move_prev();
+        assert_eq!(
+            (cursor.index(), cursor.current()),
+            (Some(11), Some(&mut 99))
+        );
+        assert_eq!(
+            contents(&list),
+            [-3, -2, -1, 0, 10, 20, 21, 11, 30, 31, 2, 99]
+        );
+    }
+
+    #[test]
+    fn cursor_splits() {
+        let mut list = numbers(0..6);
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        cursor.move_next();
+        let after = cursor.split_after();
+        assert_eq!(cursor.index(), Some(2));
+        let before = cursor.split_before();
+        assert_eq!((cursor.index(), cursor.current()), (Some(0), Some(&mut 2)));
+        assert_eq!(contents(&before), [0, 1]);
+        assert_eq!(contents(&after), [3, 4, 5]);
+        assert_eq!(contents(&list), [2]);
+
+        // at the ghost, either side is everything.
+        let mut cursor = list.cursor_front_mut();
+        cursor.move_next();
+        assert_eq!(contents(&cursor.split_before()), [2]);
+        assert_eq!(contents(&cursor.split_after()), []);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn panicking_drop_in_into_iter() {
+        let live = Cell::new(0);
+        let mut list = LinkedList::new();
+        for i in 0..6 {
+            list.push_back(Tracked::new(&live, if i == 3 { -1 } else { i }));
+        }
+        let mut cursor = list.cursor_front_mut();
+        cursor.remove_current();
+        let rest = cursor.split_after();
+        drop(list);
+        let mut iter = rest.into_iter();
+        iter.next();
+        assert_eq!(live.get(), 3);
+        // the element that panics is the first one left, the ones after it are still dropped.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(iter)));
+        assert!(result.is_err());
+        assert_eq!(live.get(), 0);
+    }
+
+    // compiles only if the list is covariant in T.
+    #[allow(dead_code)]
+    fn covariant<'a>(list: LinkedList<&'static str>) -> LinkedList<&'a str> {
+        list
+    }
+}
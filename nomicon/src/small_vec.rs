@@ -0,0 +1,800 @@
+// a Vec that keeps its first N elements inline, like the smallvec crate.
+
+use crate::custom_vec::{self, bounds, Drain, ExtractIf, RawVec, Splice, TryReserveError, Vec};
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut, Index, IndexMut, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::slice::{self, SliceIndex};
+
+enum Storage<T, const N: usize> {
+    Inline(MaybeUninit<[T; N]>),
+    // once we've spilled, we stay on the heap until shrunk.
+    Heap(RawVec<T>),
+}
+
+/// A vector that stores up to `N` elements inline, and only allocates once it grows past that.
+pub struct SmallVec<T, const N: usize> {
+    storage: Storage<T, N>,
+    len: usize,
+}
+
+impl<T, const N: usize> SmallVec<T, N> {
+    // ZSTs never need any room, so they never spill.
+    const INLINE_CAP: usize = if mem::size_of::<T>() == 0 { !0 } else { N };
+
+    pub fn new() -> Self {
+        SmallVec {
+            storage: Storage::Inline(MaybeUninit::uninit()),
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= Self::INLINE_CAP {
+            Self::new()
+        } else {
+            SmallVec {
+                storage: Storage::Heap(RawVec::with_capacity(capacity)),
+                len: 0,
+            }
+        }
+    }
+
+    /// Whether the elements have moved to the heap.
+    pub fn spilled(&self) -> bool {
+        matches!(self.storage, Storage::Heap(_))
+    }
+
+    pub fn cap(&self) -> usize {
+        match &self.storage {
+            Storage::Inline(_) => Self::INLINE_CAP,
+            Storage::Heap(buf) => buf.cap,
+        }
+    }
+
+    // unlike Vec::ptr, there are two of these: the inline buffer is part of self,
+    // so a pointer that is written through has to come from a unique borrow.
+    pub fn as_ptr(&self) -> *const T {
+        match &self.storage {
+            Storage::Inline(array) => array.as_ptr() as *const T,
+            Storage::Heap(buf) => buf.ptr.as_ptr(),
+        }
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        match &mut self.storage {
+            Storage::Inline(array) => array.as_mut_ptr() as *mut T,
+            Storage::Heap(buf) => buf.ptr.as_ptr(),
+        }
+    }
+
+    // makes room for `additional` more elements after the first `len`,
+    // moving those to the heap if they don't fit inline anymore.
+    fn try_grow(
+        &mut self,
+        len: usize,
+        additional: usize,
+        exact: bool,
+    ) -> Result<(), TryReserveError> {
+        match &mut self.storage {
+            Storage::Heap(buf) if exact => buf.try_reserve_exact(len, additional),
+            Storage::Heap(buf) => buf.try_reserve(len, additional),
+            Storage::Inline(array) => {
+                if additional <= Self::INLINE_CAP - len {
+                    return Ok(());
+                }
+                let required = len
+                    .checked_add(additional)
+                    .ok_or(TryReserveError::CapacityOverflow)?;
+                // grow geometrically, as if the inline buffer had been on the heap all along.
+                let cap = if exact {
+                    required
+                } else {
+                    required.max(N.saturating_mul(2))
+                };
+                let mut buf = RawVec::new();
+                buf.try_reserve_exact(0, cap)?;
+                // SAFETY: the first len elements are initialized, and the new buffer has room
+                // for them. they're moved, and the inline buffer never drops anything.
+                unsafe {
+                    ptr::copy_nonoverlapping(array.as_ptr() as *const T, buf.ptr.as_ptr(), len)
+                };
+                self.storage = Storage::Heap(buf);
+                Ok(())
+            }
+        }
+    }
+
+    /// Makes room for at least `additional` more elements, leaving room for further growth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(e) = self.try_grow(self.len, additional, false) {
+            e.handle()
+        }
+    }
+
+    /// Makes room for exactly `additional` more elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(e) = self.try_grow(self.len, additional, true) {
+            e.handle()
+        }
+    }
+
+    /// Like [`SmallVec::reserve`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_grow(self.len, additional, false)
+    }
+
+    /// Like [`SmallVec::reserve_exact`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_grow(self.len, additional, true)
+    }
+
+    /// Shrinks the capacity as much as possible, moving the elements back inline if they fit.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    /// Shrinks the capacity to `min_capacity`, but not below the length.
+    ///
+    /// The elements move back inline if that leaves room for no more than `N` of them.
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let cap = self.len.max(min_capacity);
+        if let Storage::Heap(buf) = &mut self.storage {
+            if cap <= Self::INLINE_CAP {
+                let mut array = MaybeUninit::<[T; N]>::uninit();
+                // SAFETY: the elements fit, and are moved. the RawVec only frees the buffer.
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        buf.ptr.as_ptr(),
+                        array.as_mut_ptr() as *mut T,
+                        self.len,
+                    )
+                };
+                self.storage = Storage::Inline(array);
+            } else if cap < buf.cap {
+                buf.shrink_to(cap);
+            }
+        }
+    }
+
+    /// Drops all elements past the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        // SAFETY: len is in bounds, and the elements past it are initialized.
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.as_mut_ptr().add(len) }, self.len - len);
+        // shorten first, so that a panicking destructor can't make us drop the tail twice.
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Truncates to `new_len`, or fills up to it with clones of `value`.
+    pub fn resize(&mut self, new_len: usize, value: T)
+    where
+        T: Clone,
+    {
+        if new_len <= self.len {
+            self.truncate(new_len);
+            return;
+        }
+        self.reserve(new_len - self.len);
+        while self.len + 1 < new_len {
+            self.push(value.clone());
+        }
+        self.push(value);
+    }
+
+    pub fn push(&mut self, elem: T) {
+        if self.len == self.cap() {
+            self.reserve(1);
+        }
+        unsafe { ptr::write(self.as_mut_ptr().add(self.len), elem) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            unsafe { Some(ptr::read(self.as_ptr().add(self.len))) }
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, elem: T) {
+        assert!(index <= self.len, "index out of bounds");
+        if self.len == self.cap() {
+            self.reserve(1);
+        }
+        unsafe {
+            let ptr = self.as_mut_ptr();
+            ptr::copy(ptr.add(index), ptr.add(index + 1), self.len - index);
+            ptr::write(ptr.add(index), elem);
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        unsafe {
+            self.len -= 1;
+            let ptr = self.as_mut_ptr();
+            let result = ptr::read(ptr.add(index));
+            ptr::copy(ptr.add(index + 1), ptr.add(index), self.len - index);
+            result
+        }
+    }
+
+    /// Removes an element and replaces it with the last one, in O(1).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        unsafe {
+            self.len -= 1;
+            let ptr = self.as_mut_ptr();
+            let result = ptr::read(ptr.add(index));
+            ptr::copy(ptr.add(self.len), ptr.add(index), 1);
+            result
+        }
+    }
+
+    /// Removes the elements in `range`, and yields them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let (start, end) = bounds(range, self.len);
+        let buf = self.as_mut_ptr();
+        // SAFETY: the drain borrows us, so the elements can't move while it lives.
+        unsafe { Drain::new(buf, NonNull::from(&mut self.len), start, end) }
+    }
+
+    /// Replaces the elements in `range` with `replace_with`, and yields the removed ones.
+    ///
+    /// The elements spill to the heap if the replacement doesn't fit inline.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Splice<'_, I::IntoIter>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+    {
+        unsafe fn reserve<T, const N: usize>(
+            owner: NonNull<()>,
+            used: usize,
+            additional: usize,
+        ) -> *mut T {
+            let v = &mut *owner.cast::<SmallVec<T, N>>().as_ptr();
+            if let Err(e) = v.try_grow(used, additional, false) {
+                e.handle()
+            }
+            v.as_mut_ptr()
+        }
+
+        let (start, end) = bounds(range, self.len);
+        let owner = NonNull::from(self);
+        // SAFETY: everything is derived from owner, which the splice borrows.
+        unsafe {
+            let v = owner.as_ptr();
+            let len = NonNull::new_unchecked(ptr::addr_of_mut!((*v).len));
+            let drain = Drain::new((*v).as_mut_ptr(), len, start, end);
+            Splice::new(
+                drain,
+                owner.cast(),
+                reserve::<T, N>,
+                replace_with.into_iter(),
+            )
+        }
+    }
+
+    /// Yields and removes the elements in `range` for which `pred` returns true.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn extract_if<R, F>(&mut self, range: R, pred: F) -> ExtractIf<'_, T, F>
+    where
+        R: RangeBounds<usize>,
+        F: FnMut(&mut T) -> bool,
+    {
+        let (start, end) = bounds(range, self.len);
+        let buf = self.as_mut_ptr();
+        // SAFETY: see drain.
+        unsafe { ExtractIf::new(buf, NonNull::from(&mut self.len), start, end, pred) }
+    }
+
+    /// Keeps only the elements for which `f` returns true.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    /// Keeps only the elements for which `f` returns true.
+    pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) {
+        self.extract_if(.., |elem| !f(elem)).for_each(drop);
+    }
+
+    /// Removes consecutive elements with the same key, keeping the first of each run.
+    pub fn dedup_by_key<K: PartialEq, F: FnMut(&mut T) -> K>(&mut self, mut key: F) {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// Removes consecutive elements for which `same(elem, previous)` returns true.
+    pub fn dedup_by<F: FnMut(&mut T, &mut T) -> bool>(&mut self, same: F) {
+        let buf = self.as_mut_ptr();
+        // SAFETY: the first len elements of the buffer are initialized.
+        unsafe { custom_vec::dedup_by(buf, &mut self.len, same) }
+    }
+
+    /// Splits off the elements from `at` on into a new vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is out of bounds.
+    pub fn split_off(&mut self, at: usize) -> SmallVec<T, N> {
+        assert!(at <= self.len, "index out of bounds");
+        let mut other = SmallVec::with_capacity(self.len - at);
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr().add(at), other.as_mut_ptr(), self.len - at);
+        }
+        other.len = self.len - at;
+        self.len = at;
+        other
+    }
+
+    /// Moves all elements of `other` to the end of this vector.
+    pub fn append<const M: usize>(&mut self, other: &mut SmallVec<T, M>) {
+        self.reserve(other.len);
+        unsafe {
+            ptr::copy_nonoverlapping(other.as_ptr(), self.as_mut_ptr().add(self.len), other.len);
+        }
+        self.len += other.len;
+        other.len = 0;
+    }
+
+    /// Converts into a [`Vec`], reusing the buffer if the elements have spilled.
+    pub fn into_vec(self) -> Vec<T> {
+        let mut v = ManuallyDrop::new(self);
+        let len = v.len;
+        // SAFETY: v is never used or dropped again, so the buffer can be moved out.
+        match unsafe { ptr::read(&v.storage) } {
+            Storage::Heap(buf) => Vec { buf, len },
+            Storage::Inline(_) => {
+                let mut vec = Vec::with_capacity(len);
+                unsafe { ptr::copy_nonoverlapping(v.as_mut_ptr(), vec.ptr(), len) };
+                vec.len = len;
+                vec
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for SmallVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for SmallVec<T, N> {
+    fn drop(&mut self) {
+        // the storage frees the buffer, if there is one.
+        self.clear();
+    }
+}
+
+impl<T, const N: usize> Deref for SmallVec<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for SmallVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for SmallVec<T, N> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for SmallVec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: Hash, const N: usize> Hash for SmallVec<T, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> Index<I> for SmallVec<T, N> {
+    type Output = I::Output;
+    fn index(&self, index: I) -> &I::Output {
+        &(**self)[index]
+    }
+}
+
+impl<T, I: SliceIndex<[T]>, const N: usize> IndexMut<I> for SmallVec<T, N> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut (**self)[index]
+    }
+}
+
+impl_eq! {
+    [const N: usize, const M: usize,] SmallVec<T, N>, SmallVec<U, M>;
+    [const N: usize,] SmallVec<T, N>, [U];
+    ['a, const N: usize,] SmallVec<T, N>, &'a [U];
+    [const N: usize, const M: usize,] SmallVec<T, N>, [U; M];
+    [const N: usize,] SmallVec<T, N>, Vec<U>;
+    [const N: usize,] [T], SmallVec<U, N>;
+    ['a, const N: usize,] &'a [T], SmallVec<U, N>;
+    [const N: usize,] Vec<T>, SmallVec<U, N>;
+}
+
+impl<T: Eq, const N: usize> Eq for SmallVec<T, N> {}
+
+impl<T: PartialOrd, const N: usize> PartialOrd for SmallVec<T, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: Ord, const N: usize> Ord for SmallVec<T, N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T, const N: usize> AsRef<[T]> for SmallVec<T, N> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> AsMut<[T]> for SmallVec<T, N> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> Borrow<[T]> for SmallVec<T, N> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const N: usize> BorrowMut<[T]> for SmallVec<T, N> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T: Clone, const N: usize> From<&[T]> for SmallVec<T, N> {
+    fn from(slice: &[T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for SmallVec<T, N> {
+    fn from(array: [T; N]) -> Self {
+        SmallVec {
+            storage: Storage::Inline(MaybeUninit::new(array)),
+            len: N,
+        }
+    }
+}
+
+/// Takes over the buffer, without copying the elements, even if they would fit inline.
+impl<T, const N: usize> From<Vec<T>> for SmallVec<T, N> {
+    fn from(v: Vec<T>) -> Self {
+        let v = ManuallyDrop::new(v);
+        let len = v.len;
+        if mem::size_of::<T>() == 0 {
+            // there is nothing to take over.
+            return SmallVec {
+                storage: Storage::Inline(MaybeUninit::uninit()),
+                len,
+            };
+        }
+        SmallVec {
+            // SAFETY: v is never used or dropped again.
+            storage: Storage::Heap(unsafe { ptr::read(&v.buf) }),
+            len,
+        }
+    }
+}
+
+impl<T, const N: usize> From<SmallVec<T, N>> for Vec<T> {
+    fn from(v: SmallVec<T, N>) -> Self {
+        v.into_vec()
+    }
+}
+
+/// The elements of a [`SmallVec`], by value.
+pub struct IntoIter<T, const N: usize> {
+    inner: IntoIterInner<T, N>,
+}
+
+enum IntoIterInner<T, const N: usize> {
+    // the inline buffer moves with the iterator, so we can't point into it, and count instead.
+    Inline {
+        array: MaybeUninit<[T; N]>,
+        start: usize,
+        end: usize,
+    },
+    Heap(custom_vec::IntoIter<T>),
+}
+
+impl<T, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        match &mut self.inner {
+            IntoIterInner::Inline { start, end, .. } if start == end => None,
+            IntoIterInner::Inline { array, start, .. } => {
+                *start += 1;
+                // SAFETY: the elements in [start, end) are initialized, and we just moved
+                // start past this one.
+                unsafe { Some(ptr::read((array.as_ptr() as *const T).add(*start - 1))) }
+            }
+            IntoIterInner::Heap(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            IntoIterInner::Inline { start, end, .. } => (end - start, Some(end - start)),
+            IntoIterInner::Heap(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<T> {
+        match &mut self.inner {
+            IntoIterInner::Inline { start, end, .. } if start == end => None,
+            IntoIterInner::Inline { array, end, .. } => {
+                *end -= 1;
+                unsafe { Some(ptr::read((array.as_ptr() as *const T).add(*end))) }
+            }
+            IntoIterInner::Heap(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> FusedIterator for IntoIter<T, N> {}
+
+impl<T, const N: usize> Drop for IntoIter<T, N> {
+    fn drop(&mut self) {
+        if let IntoIterInner::Inline { array, start, end } = &mut self.inner {
+            let remaining = ptr::slice_from_raw_parts_mut(
+                unsafe { (array.as_mut_ptr() as *mut T).add(*start) },
+                *end - *start,
+            );
+            // empty the iterator first, so that nothing is dropped twice if a destructor panics.
+            *start = *end;
+            unsafe { ptr::drop_in_place(remaining) };
+        }
+        // the heap iterator drops its own elements.
+    }
+}
+
+impl<T, const N: usize> IntoIterator for SmallVec<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
+    fn into_iter(self) -> IntoIter<T, N> {
+        let v = ManuallyDrop::new(self);
+        let len = v.len;
+        // SAFETY: v is never used or dropped again, so the buffer can be moved out.
+        let inner = match unsafe { ptr::read(&v.storage) } {
+            Storage::Inline(array) => IntoIterInner::Inline {
+                array,
+                start: 0,
+                end: len,
+            },
+            Storage::Heap(buf) => IntoIterInner::Heap(Vec { buf, len }.into_iter()),
+        };
+        IntoIter { inner }
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a SmallVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut SmallVec<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for SmallVec<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = SmallVec::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<T, const N: usize> Extend<T> for SmallVec<T, N> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a, const N: usize> Extend<&'a T> for SmallVec<T, N> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    #[test]
+    fn inline_then_spill() {
+        let mut v: SmallVec<i32, 4> = SmallVec::new();
+        assert_eq!(v.cap(), 4);
+        v.extend(0..4);
+        assert!(!v.spilled());
+        // the elements live inside the vector itself.
+        let inline = &v as *const _ as usize;
+        assert!((inline..inline + mem::size_of_val(&v)).contains(&(v.as_ptr() as usize)));
+
+        v.push(4);
+        assert!(v.spilled());
+        assert_eq!(v.cap(), 8);
+        v.insert(0, -1);
+        assert_eq!(v.remove(1), 0);
+        assert_eq!(v.swap_remove(0), -1);
+        assert_eq!(v, [4, 1, 2, 3]);
+
+        v.shrink_to_fit();
+        assert!(!v.spilled());
+        assert_eq!(v.pop(), Some(3));
+        assert_eq!(v.split_off(1), [1, 2]);
+        let mut other: SmallVec<i32, 1> = SmallVec::from([5]);
+        v.append(&mut other);
+        assert_eq!((&*v, other.len()), (&[4, 5][..], 0));
+
+        let v: SmallVec<i32, 2> = (0..10).collect();
+        let vec = v.into_vec();
+        assert_eq!(vec, (0..10).collect::<Vec<_>>());
+        let back: SmallVec<i32, 2> = vec.into();
+        assert_eq!(back.into_iter().rev().collect::<Vec<_>>()[..3], [9, 8, 7]);
+    }
+
+    #[test]
+    fn drain_splice_retain() {
+        let mut v: SmallVec<i32, 8> = (0..6).collect();
+        assert_eq!(v.drain(1..3).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(v, [0, 3, 4, 5]);
+        assert_eq!(v.splice(..1, [1, 2]).collect::<Vec<_>>(), [0]);
+        assert_eq!(v.drain(..2).collect::<Vec<_>>(), [1, 2]);
+        assert!(!v.spilled());
+
+        // the replacement doesn't fit inline.
+        let removed: Vec<_> = v.splice(1..2, 10..17).collect();
+        assert_eq!(removed, [4]);
+        assert!(v.spilled());
+        assert_eq!(v, [3, 10, 11, 12, 13, 14, 15, 16, 5]);
+
+        v.retain(|x| x % 2 == 0);
+        assert_eq!(v, [10, 12, 14, 16]);
+        assert_eq!(
+            v.extract_if(.., |x| *x > 10).collect::<Vec<_>>(),
+            [12, 14, 16]
+        );
+        v.resize(6, 7);
+        v.dedup_by_key(|x| *x);
+        assert_eq!(v, [10, 7]);
+    }
+
+    #[test]
+    fn drops_every_element_once() {
+        let rc = Rc::new(());
+        let make = |n| (0..n).map(|_| Rc::clone(&rc)).collect::<SmallVec<_, 3>>();
+
+        for &n in &[2, 5] {
+            let mut v = make(n);
+            drop(v.drain(1..).next());
+            let mut iter = make(n).into_iter();
+            iter.next();
+            drop(iter);
+            let clone = v.clone();
+            v.truncate(0);
+            drop(clone);
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        let mut v: SmallVec<(), 0> = SmallVec::new();
+        for _ in 0..1000 {
+            v.push(());
+        }
+        assert!(!v.spilled());
+        assert_eq!(v.cap(), usize::MAX);
+        assert_eq!(v.drain(10..).count(), 990);
+        assert_eq!(v.into_iter().count(), 10);
+    }
+
+    #[test]
+    fn panic_safety() {
+        // clones panic after the third one.
+        struct Bomb(Rc<()>);
+
+        impl Clone for Bomb {
+            fn clone(&self) -> Self {
+                if Rc::strong_count(&self.0) > 3 {
+                    panic!("boom");
+                }
+                Bomb(Rc::clone(&self.0))
+            }
+        }
+
+        let rc = Rc::new(());
+        let mut v: SmallVec<Bomb, 2> = SmallVec::new();
+        v.push(Bomb(Rc::clone(&rc)));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| v.resize(4, Bomb(Rc::clone(&rc)))));
+        assert!(result.is_err());
+        // the clones made before the panic made it in, and the value was dropped.
+        assert_eq!(v.len(), 2);
+        assert!(v.spilled());
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        // a panicking predicate leaves the remaining elements where they were.
+        let mut v: SmallVec<i32, 4> = SmallVec::from([1, 2, 3, 4]);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            v.retain(|&x| if x == 3 { panic!("boom") } else { x != 2 })
+        }));
+        assert!(result.is_err());
+        assert_eq!(v, [1, 3, 4]);
+    }
+}
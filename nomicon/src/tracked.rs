@@ -0,0 +1,31 @@
+// a test element shared by the collections' tests.
+
+use std::cell::Cell;
+
+// counts the live instances of itself, and panics on drop if its value is negative.
+pub(crate) struct Tracked<'a> {
+    pub(crate) live: &'a Cell<usize>,
+    pub(crate) value: i32,
+}
+
+impl<'a> Tracked<'a> {
+    pub(crate) fn new(live: &'a Cell<usize>, value: i32) -> Self {
+        live.set(live.get() + 1);
+        Tracked { live, value }
+    }
+}
+
+impl Clone for Tracked<'_> {
+    fn clone(&self) -> Self {
+        Tracked::new(self.live, self.value)
+    }
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        self.live.set(self.live.get() - 1);
+        if self.value < 0 {
+            panic!("dropped {}", self.value);
+        }
+    }
+}
@@ -0,0 +1,927 @@
+// a growable ring buffer, on the same RawVec as the Vec.
+
+use crate::custom_vec::{bounds, RawVec, TryReserveError, Vec};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::{FromIterator, FusedIterator};
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Index, IndexMut, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// A double-ended queue, which can push and pop at both ends in O(1).
+pub struct VecDeque<T> {
+    buf: RawVec<T>,
+    // the physical index of the first element. the rest follow it, wrapping around at the
+    // end of the buffer.
+    head: usize,
+    len: usize,
+}
+
+impl<T> VecDeque<T> {
+    pub fn ptr(&self) -> *mut T {
+        self.buf.ptr.as_ptr()
+    }
+
+    pub fn cap(&self) -> usize {
+        self.buf.cap
+    }
+
+    pub fn new() -> Self {
+        VecDeque {
+            buf: RawVec::new(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        VecDeque {
+            buf: RawVec::with_capacity(capacity),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // `idx + add` modulo the capacity, without overflowing, even for ZSTs with cap = usize::MAX.
+    // idx must be below the capacity, and add at most the capacity.
+    fn wrap_add(&self, idx: usize, add: usize) -> usize {
+        let rest = self.cap() - idx;
+        if add >= rest {
+            add - rest
+        } else {
+            idx + add
+        }
+    }
+
+    fn wrap_sub(&self, idx: usize, sub: usize) -> usize {
+        if sub > idx {
+            self.cap() - (sub - idx)
+        } else {
+            idx - sub
+        }
+    }
+
+    // where the element at logical index `idx` is in the buffer.
+    fn physical(&self, idx: usize) -> usize {
+        self.wrap_add(self.head, idx)
+    }
+
+    fn try_grow(&mut self, additional: usize, exact: bool) -> Result<(), TryReserveError> {
+        let old_cap = self.cap();
+        if exact {
+            self.buf.try_reserve_exact(self.len, additional)?;
+        } else {
+            self.buf.try_reserve(self.len, additional)?;
+        }
+        if self.cap() == old_cap || self.head <= old_cap - self.len {
+            // still contiguous.
+            return Ok(());
+        }
+        // the elements wrapped around the old end of the buffer, which is now in the middle.
+        // move whichever part is shorter, and fits.
+        let head_len = old_cap - self.head;
+        let tail_len = self.len - head_len;
+        // SAFETY: the elements are moved to free slots in the buffer.
+        unsafe {
+            if tail_len < head_len && self.cap() - old_cap >= tail_len {
+                // [DE..ABC] -> [...ABCDE.]
+                ptr::copy_nonoverlapping(self.ptr(), self.ptr().add(old_cap), tail_len);
+            } else {
+                // [DE..ABC] -> [DE.....ABC]
+                let new_head = self.cap() - head_len;
+                ptr::copy(
+                    self.ptr().add(self.head),
+                    self.ptr().add(new_head),
+                    head_len,
+                );
+                self.head = new_head;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes room for at least `additional` more elements, leaving room for further growth.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(e) = self.try_grow(additional, false) {
+            e.handle()
+        }
+    }
+
+    /// Makes room for exactly `additional` more elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity would exceed `isize::MAX` bytes.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(e) = self.try_grow(additional, true) {
+            e.handle()
+        }
+    }
+
+    /// Like [`VecDeque::reserve`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_grow(additional, false)
+    }
+
+    /// Like [`VecDeque::reserve_exact`], but returns an error instead of panicking or aborting.
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.try_grow(additional, true)
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        if self.len == self.cap() {
+            self.reserve(1);
+        }
+        unsafe { ptr::write(self.ptr().add(self.physical(self.len)), elem) };
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        if self.len == self.cap() {
+            self.reserve(1);
+        }
+        self.head = self.wrap_sub(self.head, 1);
+        unsafe { ptr::write(self.ptr().add(self.head), elem) };
+        self.len += 1;
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(ptr::read(self.ptr().add(self.physical(self.len)))) }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let old_head = self.head;
+        self.head = self.physical(1);
+        self.len -= 1;
+        unsafe { Some(ptr::read(self.ptr().add(old_head))) }
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        if idx < self.len {
+            unsafe { Some(&*self.ptr().add(self.physical(idx))) }
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        if idx < self.len {
+            unsafe { Some(&mut *self.ptr().add(self.physical(idx))) }
+        } else {
+            None
+        }
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.wrapping_sub(1))
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(self.len.wrapping_sub(1))
+    }
+
+    // the physical ranges of the elements at logical [start, start + len),
+    // before and after the end of the buffer.
+    fn slice_ranges(&self, start: usize, len: usize) -> ((usize, usize), (usize, usize)) {
+        let first = self.physical(start);
+        let room = self.cap() - first;
+        if len <= room {
+            ((first, len), (0, 0))
+        } else {
+            ((first, room), (0, len - room))
+        }
+    }
+
+    // raw slices of the elements at logical [start, start + len), see slice_ranges.
+    fn raw_slices(&self, start: usize, len: usize) -> (*mut [T], *mut [T]) {
+        let ((a, a_len), (b, b_len)) = self.slice_ranges(start, len);
+        unsafe {
+            (
+                ptr::slice_from_raw_parts_mut(self.ptr().add(a), a_len),
+                ptr::slice_from_raw_parts_mut(self.ptr().add(b), b_len),
+            )
+        }
+    }
+
+    /// The elements in order, as the part up to the end of the buffer and the part that
+    /// wrapped around to its start.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (a, b) = self.raw_slices(0, self.len);
+        unsafe { (&*a, &*b) }
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (a, b) = self.raw_slices(0, self.len);
+        unsafe { (&mut *a, &mut *b) }
+    }
+
+    /// Moves the elements around so that they don't wrap, and returns them as one slice.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        let cap = self.cap();
+        if self.head > cap - self.len {
+            let head_len = cap - self.head;
+            let tail_len = self.len - head_len;
+            let free = cap - self.len;
+            let ptr = self.ptr();
+            // SAFETY: the elements are only moved within the buffer, and into free slots.
+            unsafe {
+                if free >= head_len {
+                    // [DEFGH....ABC] -> [ABCDEFGH....]
+                    ptr::copy(ptr, ptr.add(head_len), tail_len);
+                    ptr::copy_nonoverlapping(ptr.add(self.head), ptr, head_len);
+                    self.head = 0;
+                } else if free >= tail_len {
+                    // [FGH....ABCDE] -> [...ABCDEFGH.]
+                    ptr::copy(ptr.add(self.head), ptr.add(tail_len), head_len);
+                    ptr::copy_nonoverlapping(ptr, ptr.add(self.len), tail_len);
+                    self.head = tail_len;
+                } else if head_len > tail_len {
+                    // [EFG.ABCD] -> [.EFGABCD] -> [.ABCDEFG]
+                    ptr::copy(ptr, ptr.add(free), tail_len);
+                    slice::from_raw_parts_mut(ptr.add(free), self.len).rotate_left(tail_len);
+                    self.head = free;
+                } else {
+                    // [CDEFG.AB] -> [CDEFGAB.] -> [ABCDEFG.]
+                    ptr::copy(ptr.add(self.head), ptr.add(tail_len), head_len);
+                    slice::from_raw_parts_mut(ptr, self.len).rotate_right(head_len);
+                    self.head = 0;
+                }
+            }
+        }
+        self.as_mut_slices().0
+    }
+
+    /// Rotates the elements `n` places to the front, so that the element at `n` comes first.
+    ///
+    /// Moves `min(n, len - n)` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the length.
+    pub fn rotate_left(&mut self, n: usize) {
+        assert!(n <= self.len, "rotating by more than the length");
+        if n <= self.len / 2 {
+            self.move_front_to_back(n);
+        } else {
+            self.move_back_to_front(self.len - n);
+        }
+    }
+
+    /// Rotates the elements `n` places to the back, so that the last `n` elements come first.
+    ///
+    /// Moves `min(n, len - n)` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the length.
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(n <= self.len, "rotating by more than the length");
+        if n <= self.len / 2 {
+            self.move_back_to_front(n);
+        } else {
+            self.move_front_to_back(self.len - n);
+        }
+    }
+
+    // popping first always leaves room for the push, so these never reallocate.
+    fn move_front_to_back(&mut self, n: usize) {
+        for _ in 0..n {
+            let elem = self.pop_front().unwrap();
+            self.push_back(elem);
+        }
+    }
+
+    fn move_back_to_front(&mut self, n: usize) {
+        for _ in 0..n {
+            let elem = self.pop_back().unwrap();
+            self.push_front(elem);
+        }
+    }
+
+    /// Drops all elements past the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let (a, b) = self.raw_slices(len, self.len - len);
+        // shorten first, so that a panicking destructor can't make us drop the tail twice.
+        self.len = len;
+        unsafe { drop_both(a, b) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Removes the elements in `range`, and yields them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T> {
+        let (start, end) = bounds(range, self.len);
+        let tail_len = self.len - end;
+        // like Vec::drain, the deque only keeps the elements in front of the range until
+        // the drain is dropped, so that leaking it leaks the rest.
+        self.len = start;
+        Drain {
+            deque: NonNull::from(self),
+            start,
+            idx: 0,
+            remaining: end - start,
+            drain_len: end - start,
+            tail_len,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (front, back) = self.as_slices();
+        Iter {
+            front: front.iter(),
+            back: back.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (front, back) = self.as_mut_slices();
+        IterMut {
+            front: front.iter_mut(),
+            back: back.iter_mut(),
+        }
+    }
+}
+
+// drops the elements in a, then in b, even if dropping one of those in a panics.
+unsafe fn drop_both<T>(a: *mut [T], b: *mut [T]) {
+    struct DropB<T>(*mut [T]);
+
+    impl<T> Drop for DropB<T> {
+        fn drop(&mut self) {
+            unsafe { ptr::drop_in_place(self.0) };
+        }
+    }
+
+    let _b = DropB(b);
+    ptr::drop_in_place(a);
+}
+
+impl<T> Default for VecDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for VecDeque<T> {
+    fn drop(&mut self) {
+        // the RawVec frees the buffer afterwards.
+        self.clear();
+    }
+}
+
+impl<T: Clone> Clone for VecDeque<T> {
+    fn clone(&self) -> Self {
+        // the clone starts out contiguous.
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for VecDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// the elements may be split up differently, so these can't just compare the slices.
+impl<T: PartialEq<U>, U> PartialEq<VecDeque<U>> for VecDeque<T> {
+    fn eq(&self, other: &VecDeque<U>) -> bool {
+        self.len == other.len && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<[U]> for VecDeque<T> {
+    fn eq(&self, other: &[U]) -> bool {
+        let (a, b) = self.as_slices();
+        self.len == other.len() && a == &other[..a.len()] && b == &other[a.len()..]
+    }
+}
+
+impl<T: PartialEq<U>, U, const N: usize> PartialEq<[U; N]> for VecDeque<T> {
+    fn eq(&self, other: &[U; N]) -> bool {
+        *self == other[..]
+    }
+}
+
+impl<T: Eq> Eq for VecDeque<T> {}
+
+impl<T: PartialOrd> PartialOrd for VecDeque<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for VecDeque<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for VecDeque<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // like a slice, but independent of where the elements wrap.
+        self.len.hash(state);
+        self.iter().for_each(|elem| elem.hash(state));
+    }
+}
+
+impl<T> Index<usize> for VecDeque<T> {
+    type Output = T;
+    fn index(&self, idx: usize) -> &T {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+impl<T> IndexMut<usize> for VecDeque<T> {
+    fn index_mut(&mut self, idx: usize) -> &mut T {
+        self.get_mut(idx).expect("index out of bounds")
+    }
+}
+
+/// Takes over the buffer, without copying the elements.
+impl<T> From<Vec<T>> for VecDeque<T> {
+    fn from(v: Vec<T>) -> Self {
+        let v = ManuallyDrop::new(v);
+        VecDeque {
+            // SAFETY: v is never used or dropped again.
+            buf: unsafe { ptr::read(&v.buf) },
+            head: 0,
+            len: v.len,
+        }
+    }
+}
+
+/// Hands over the buffer, moving the elements to its start first.
+impl<T> From<VecDeque<T>> for Vec<T> {
+    fn from(mut deque: VecDeque<T>) -> Self {
+        deque.make_contiguous();
+        let deque = ManuallyDrop::new(deque);
+        unsafe {
+            if deque.head != 0 {
+                ptr::copy(deque.ptr().add(deque.head), deque.ptr(), deque.len);
+            }
+            Vec {
+                buf: ptr::read(&deque.buf),
+                len: deque.len,
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for VecDeque<T> {
+    fn from(array: [T; N]) -> Self {
+        Vec::from(array).into()
+    }
+}
+
+/// The elements removed by [`VecDeque::drain`].
+///
+/// The gap left by the drained range is closed when this is dropped, by moving whichever
+/// of the elements in front of it and behind it are fewer.
+pub struct Drain<'a, T: 'a> {
+    // a pointer rather than a reference, since we read the elements out of its buffer.
+    deque: NonNull<VecDeque<T>>,
+    // the logical index of the range.
+    start: usize,
+    // the elements that haven't been yielded are [start + idx, start + idx + remaining).
+    idx: usize,
+    remaining: usize,
+    drain_len: usize,
+    // the number of elements after the range.
+    tail_len: usize,
+    _marker: PhantomData<&'a mut VecDeque<T>>,
+}
+
+// like the &mut VecDeque<T> it stands for.
+unsafe impl<T: Send> Send for Drain<'_, T> {}
+unsafe impl<T: Sync> Sync for Drain<'_, T> {}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        unsafe {
+            let deque = self.deque.as_ref();
+            let elem = ptr::read(deque.ptr().add(deque.physical(self.start + self.idx)));
+            self.idx += 1;
+            self.remaining -= 1;
+            Some(elem)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        unsafe {
+            let deque = self.deque.as_ref();
+            let last = deque.physical(self.start + self.idx + self.remaining);
+            Some(ptr::read(deque.ptr().add(last)))
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        // closes the gap, even if dropping one of the remaining elements panics.
+        struct JoinGap<'r, 'a, T>(&'r mut Drain<'a, T>);
+
+        impl<T> Drop for JoinGap<'_, '_, T> {
+            fn drop(&mut self) {
+                let drain = &mut *self.0;
+                let (head_len, gap, tail_len) = (drain.start, drain.drain_len, drain.tail_len);
+                let deque = unsafe { drain.deque.as_mut() };
+                let ptr = deque.ptr();
+                // SAFETY: the gap has been moved out of, and each element is moved into it
+                // before its own slot is reused.
+                unsafe {
+                    if gap == 0 {
+                        // nothing was drained, so nothing has to move.
+                    } else if head_len <= tail_len {
+                        // move the front part back, starting with its last element.
+                        for i in (0..head_len).rev() {
+                            let (src, dst) = (deque.physical(i), deque.physical(i + gap));
+                            ptr::copy_nonoverlapping(ptr.add(src), ptr.add(dst), 1);
+                        }
+                        deque.head = deque.physical(gap);
+                    } else {
+                        // move the back part forward, starting with its first element.
+                        for i in head_len..head_len + tail_len {
+                            let (src, dst) = (deque.physical(i + gap), deque.physical(i));
+                            ptr::copy_nonoverlapping(ptr.add(src), ptr.add(dst), 1);
+                        }
+                    }
+                }
+                deque.len = head_len + tail_len;
+            }
+        }
+
+        let guard = JoinGap(self);
+        let drain = &mut *guard.0;
+        let (a, b) =
+            unsafe { drain.deque.as_ref() }.raw_slices(drain.start + drain.idx, drain.remaining);
+        // empty the iterator first, so that nothing is dropped twice if a destructor panics.
+        drain.remaining = 0;
+        unsafe { drop_both(a, b) };
+    }
+}
+
+/// The elements of a [`VecDeque`], by reference.
+#[derive(Clone)]
+pub struct Iter<'a, T> {
+    // the part before the end of the buffer, and the part that wrapped around.
+    front: slice::Iter<'a, T>,
+    back: slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// The elements of a [`VecDeque`], by mutable reference.
+pub struct IterMut<'a, T> {
+    front: slice::IterMut<'a, T>,
+    back: slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// The elements of a [`VecDeque`], by value.
+pub struct IntoIter<T> {
+    // popping from a deque is already all we need.
+    deque: VecDeque<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.deque.pop_front()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.deque.len, Some(self.deque.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.deque.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for VecDeque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { deque: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a VecDeque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut VecDeque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T> FromIterator<T> for VecDeque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut deque = VecDeque::new();
+        deque.extend(iter);
+        deque
+    }
+}
+
+impl<T> Extend<T> for VecDeque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a> Extend<&'a T> for VecDeque<T> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use crate::tracked::Tracked;
+    use std::panic::{self, AssertUnwindSafe};
+
+    // a deque with the given capacity, whose `len` elements 0, 1, ... start at physical `head`.
+    fn at(cap: usize, head: usize, len: usize) -> VecDeque<usize> {
+        let mut deque = VecDeque::with_capacity(cap);
+        for _ in 0..head {
+            deque.push_back(0);
+            deque.pop_front();
+        }
+        deque.extend(0..len);
+        assert_eq!((deque.cap(), deque.head), (cap, head % cap));
+        deque
+    }
+
+    fn contents(deque: &VecDeque<usize>) -> std::vec::Vec<usize> {
+        deque.iter().copied().collect()
+    }
+
+    #[test]
+    fn push_pop_both_ends() {
+        let mut deque = VecDeque::with_capacity(4);
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        deque.push_front(0);
+        // the front wrapped around to the end of the buffer.
+        assert_eq!(deque.as_slices(), (&[0, 1][..], &[2, 3][..]));
+        assert_eq!((deque.front(), deque.back()), (Some(&0), Some(&3)));
+
+        // growing keeps the order.
+        deque.push_front(-1);
+        deque.push_back(4);
+        assert_eq!(deque, [-1, 0, 1, 2, 3, 4]);
+        assert_eq!(deque[1], 0);
+        *deque.back_mut().unwrap() = 5;
+
+        assert_eq!(deque.pop_front(), Some(-1));
+        assert_eq!(deque.pop_back(), Some(5));
+        assert_eq!(
+            deque.iter().rev().copied().collect::<std::vec::Vec<_>>(),
+            [3, 2, 1, 0]
+        );
+        let mut iter = deque.into_iter();
+        assert_eq!(
+            (iter.next(), iter.next_back(), iter.len()),
+            (Some(0), Some(3), 2)
+        );
+    }
+
+    #[test]
+    fn growing_moves_the_shorter_part() {
+        for head in 0..4 {
+            for len in 0..=4 {
+                let mut deque = at(4, head, len);
+                deque.reserve(5);
+                assert_eq!(contents(&deque), (0..len).collect::<std::vec::Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn make_contiguous_every_layout() {
+        let cap = 7;
+        for head in 0..cap {
+            for len in 0..=cap {
+                let mut deque = at(cap, head, len);
+                let expected: std::vec::Vec<_> = (0..len).collect();
+                assert_eq!(deque.make_contiguous(), &expected[..]);
+                assert_eq!(deque.as_slices(), (&expected[..], &[][..]));
+            }
+        }
+    }
+
+    #[test]
+    fn rotate() {
+        let mut deque = at(5, 3, 5);
+        deque.rotate_left(1);
+        assert_eq!(deque, [1, 2, 3, 4, 0]);
+        deque.rotate_left(4);
+        assert_eq!(deque, [0, 1, 2, 3, 4]);
+        deque.rotate_right(2);
+        assert_eq!(deque, [3, 4, 0, 1, 2]);
+        deque.rotate_right(5);
+        assert_eq!(deque, [3, 4, 0, 1, 2]);
+    }
+
+    #[test]
+    fn drain_every_range() {
+        let cap = 6;
+        for head in 0..cap {
+            for len in 0..=cap {
+                for start in 0..=len {
+                    for end in start..=len {
+                        let mut deque = at(cap, head, len);
+                        let drained: std::vec::Vec<_> = deque.drain(start..end).collect();
+                        assert_eq!(drained, (start..end).collect::<std::vec::Vec<_>>());
+                        let rest: std::vec::Vec<_> = (0..start).chain(end..len).collect();
+                        assert_eq!(contents(&deque), rest);
+                    }
+                }
+            }
+        }
+
+        let mut deque = at(6, 4, 6);
+        let mut drain = deque.drain(1..5);
+        assert_eq!((drain.next_back(), drain.next()), (Some(4), Some(1)));
+        drop(drain);
+        assert_eq!(deque, [0, 5]);
+    }
+
+    #[test]
+    fn forgotten_drain_leaks_instead_of_double_dropping() {
+        let live = Cell::new(0);
+        let mut deque = VecDeque::with_capacity(4);
+        for i in 0..3 {
+            deque.push_back(Tracked::new(&live, i));
+            deque.push_front(Tracked::new(&live, i));
+        }
+        deque.drain(1..3).next();
+        assert_eq!(live.get(), 4);
+        // the drained element and the one after it are leaked, the ones in front of it stay.
+        std::mem::forget(deque.drain(2..3));
+        assert_eq!(deque.len(), 2);
+        let mut iter = deque.clone().into_iter();
+        iter.next();
+        drop(iter);
+        deque.truncate(1);
+        assert_eq!(live.get(), 3);
+        drop(deque);
+        assert_eq!(live.get(), 2);
+    }
+
+    #[test]
+    fn panicking_drop_in_drain() {
+        let live = Cell::new(0);
+        let mut deque = VecDeque::with_capacity(4);
+        for &value in &[0, -1, 2, 3] {
+            deque.push_front(Tracked::new(&live, value));
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(deque.drain(..3))));
+        assert!(result.is_err());
+        // every drained element was dropped, and the last one moved up.
+        assert_eq!((live.get(), deque.len()), (1, 1));
+        assert_eq!(deque[0].value, 0);
+    }
+
+    #[test]
+    fn zero_sized_elements_wrap_at_usize_max() {
+        let mut deque = VecDeque::new();
+        assert_eq!(deque.cap(), usize::MAX);
+        for _ in 0..10 {
+            deque.push_front(());
+            deque.push_back(());
+        }
+        assert_eq!(deque.head, usize::MAX - 10);
+        assert_eq!(deque.as_slices().0.len() + deque.as_slices().1.len(), 20);
+        assert_eq!(deque.make_contiguous().len(), 20);
+        deque.rotate_left(3);
+        assert_eq!(deque.drain(5..).rev().count(), 15);
+        assert_eq!(deque.into_iter().count(), 5);
+    }
+
+    #[test]
+    fn conversions() {
+        let v = Vec::from(at(4, 3, 3));
+        assert_eq!(v, [0, 1, 2]);
+        let mut deque = VecDeque::from(v);
+        deque.push_front(9);
+        assert_eq!(format!("{:?}", deque), "[9, 0, 1, 2]");
+        assert!(deque > at(4, 0, 3));
+    }
+}
@@ -0,0 +1,160 @@
+//! Allocators that [`Rc`](crate::rc::Rc) can place its allocations in.
+//!
+//! This is a small stable stand-in for the unstable `std::alloc::Allocator` API.
+
+use crate::cell::Cell;
+use std::alloc::Layout;
+use std::fmt;
+use std::ptr::{self, NonNull};
+
+/// The memory could not be allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+/// Something that hands out and takes back memory.
+///
+/// # Safety
+///
+/// Memory returned by `allocate` must be valid for the given layout until it is passed to
+/// `deallocate` of this allocator, or of a clone of it.
+pub unsafe trait Allocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError>;
+
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `allocate` of this allocator with the same `layout`,
+    /// and must not have been deallocated yet.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+/// The global allocator, as used by `Box` and `Vec`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        if layout.size() == 0 {
+            // the global allocator doesn't support zero-sized allocations,
+            // but any well-aligned pointer is valid for zero bytes.
+            // SAFETY: alignments are never zero.
+            return Ok(unsafe {
+                NonNull::new_unchecked(ptr::without_provenance_mut(layout.align()))
+            });
+        }
+        // SAFETY: the layout is not zero-sized.
+        NonNull::new(unsafe { std::alloc::alloc(layout) }).ok_or(AllocError)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            std::alloc::dealloc(ptr.as_ptr(), layout);
+        }
+    }
+}
+
+/// An allocator that keeps track of what is allocated through it, to find leaks in tests.
+///
+/// ```
+/// use smart_pointers::alloc::CountingAllocator;
+/// use smart_pointers::rc::Rc;
+///
+/// let counter = CountingAllocator::new();
+/// let rc = Rc::new_in(5, &counter);
+/// let weak = Rc::downgrade(&rc);
+/// drop(rc);
+/// assert_eq!(counter.live_allocations(), 1);
+/// drop(weak);
+/// counter.assert_no_leaks();
+/// ```
+#[derive(Debug)]
+pub struct CountingAllocator<A: Allocator = Global> {
+    inner: A,
+    live_allocations: Cell<usize>,
+    live_bytes: Cell<usize>,
+    total_allocations: Cell<usize>,
+}
+
+impl CountingAllocator {
+    pub fn new() -> Self {
+        Self::wrap(Global)
+    }
+}
+
+impl Default for CountingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Allocator> CountingAllocator<A> {
+    /// Counts the allocations made through `inner`.
+    pub fn wrap(inner: A) -> Self {
+        CountingAllocator {
+            inner,
+            live_allocations: Cell::new(0),
+            live_bytes: Cell::new(0),
+            total_allocations: Cell::new(0),
+        }
+    }
+
+    /// The number of allocations that haven't been freed yet.
+    pub fn live_allocations(&self) -> usize {
+        self.live_allocations.get()
+    }
+
+    /// The number of bytes that haven't been freed yet.
+    pub fn live_bytes(&self) -> usize {
+        self.live_bytes.get()
+    }
+
+    /// The number of allocations ever made.
+    pub fn total_allocations(&self) -> usize {
+        self.total_allocations.get()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if any allocation hasn't been freed.
+    #[track_caller]
+    pub fn assert_no_leaks(&self) {
+        assert_eq!(
+            (self.live_allocations(), self.live_bytes()),
+            (0, 0),
+            "leaked (allocations, bytes)"
+        );
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for CountingAllocator<A> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        self.live_allocations.set(self.live_allocations.get() + 1);
+        self.live_bytes.set(self.live_bytes.get() + layout.size());
+        self.total_allocations.set(self.total_allocations.get() + 1);
+        Ok(ptr)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live_allocations.set(self.live_allocations.get() - 1);
+        self.live_bytes.set(self.live_bytes.get() - layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+}
@@ -1,6 +1,10 @@
 use std::cell::UnsafeCell;
+use std::cmp::Ordering;
+use std::{fmt, mem, ptr};
 
-pub struct Cell<T> {
+// repr(transparent) so that a &mut T can be viewed as a &Cell<T>, see from_mut.
+#[repr(transparent)]
+pub struct Cell<T: ?Sized> {
     value: UnsafeCell<T>,
 }
 
@@ -15,9 +19,9 @@ impl<T> Cell<T> {
     }
 
     pub fn set(&self, value: T) {
-        // SAFETY: we know no-one else is concurrently mutating self.value (because !Sync)
-        // SAFETY: we know we're not invalidating any references, because we never give any out
-        unsafe { *self.value.get() = value };
+        // the old value is dropped here, after we're done touching the cell,
+        // in case its Drop impl accesses this cell again.
+        drop(self.replace(value));
     }
 
     pub fn get(&self) -> T
@@ -28,4 +32,163 @@ impl<T> Cell<T> {
         // (because !Sync), and it is executing this function instead.
         unsafe { *self.value.get() }
     }
+
+    /// Sets the value, returning the old one.
+    pub fn replace(&self, value: T) -> T {
+        // SAFETY: we know no-one else is concurrently mutating self.value (because !Sync)
+        // SAFETY: we know we're not invalidating any references, because we never give any out
+        unsafe { mem::replace(&mut *self.value.get(), value) }
+    }
+
+    /// Takes the value, leaving `Default::default()` in its place.
+    pub fn take(&self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+
+    /// Swaps the values of two cells.
+    pub fn swap(&self, other: &Self) {
+        // swapping a cell with itself is a no-op, but ptr::swap requires distinct places
+        // to not overlap, so we handle it separately.
+        if ptr::eq(self, other) {
+            return;
+        }
+        // SAFETY: both cells are !Sync, so no other thread can touch them,
+        // and we never give out references to the values, so we don't invalidate any.
+        unsafe { ptr::swap(self.value.get(), other.value.get()) }
+    }
+
+    /// Updates the value with a function of the current value.
+    pub fn update(&self, f: impl FnOnce(T) -> T)
+    where
+        T: Copy,
+    {
+        // f runs while no reference into the cell exists, so it may access the cell itself.
+        self.set(f(self.get()));
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Cell<T> {
+    /// Returns a mutable reference to the value.
+    ///
+    /// Statically safe, since we have the only reference to the cell.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Views a mutable reference as a cell, for the duration of the borrow.
+    pub fn from_mut(t: &mut T) -> &Cell<T> {
+        // SAFETY: Cell<T> has the same layout as T (repr(transparent) over UnsafeCell<T>),
+        // and we hold the only reference to t for the returned lifetime,
+        // so it is fine to mutate it through shared references to the cell.
+        unsafe { &*(t as *mut T as *const Cell<T>) }
+    }
+}
+
+impl<T> Cell<[T]> {
+    /// Views a cell of a slice as a slice of cells.
+    pub fn as_slice_of_cells(&self) -> &[Cell<T>] {
+        // SAFETY: Cell<T> has the same layout as T, so [Cell<T>] has the same layout as [T].
+        // the cells are !Sync, so the elements are still only mutated from this thread.
+        unsafe { &*(self as *const Cell<[T]> as *const [Cell<T>]) }
+    }
+}
+
+impl<T: Copy> Clone for Cell<T> {
+    fn clone(&self) -> Self {
+        Cell::new(self.get())
+    }
+}
+
+impl<T: Default> Default for Cell<T> {
+    fn default() -> Self {
+        Cell::new(T::default())
+    }
+}
+
+impl<T> From<T> for Cell<T> {
+    fn from(value: T) -> Self {
+        Cell::new(value)
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for Cell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cell").field("value", &self.get()).finish()
+    }
+}
+
+// the comparisons copy the values out, since we can't hand out references into a cell.
+impl<T: Copy + PartialEq> PartialEq for Cell<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T: Copy + Eq> Eq for Cell<T> {}
+
+impl<T: Copy + PartialOrd> PartialOrd for Cell<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.get().partial_cmp(&other.get())
+    }
+}
+
+impl<T: Copy + Ord> Ord for Cell<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(&other.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_copy_values() {
+        let c = Cell::new(String::from("hello"));
+        assert_eq!(c.replace(String::from("world")), "hello");
+        assert_eq!(c.take(), "world");
+        assert_eq!(c.into_inner(), "");
+    }
+
+    #[test]
+    fn swap_and_update() {
+        let a = Cell::new(1);
+        let b = Cell::new(2);
+        a.swap(&b);
+        a.swap(&a);
+        assert_eq!((a.get(), b.get()), (2, 1));
+        a.update(|x| x * 10);
+        assert_eq!(a.get(), 20);
+    }
+
+    #[test]
+    fn slice_of_cells() {
+        let mut v = [1, 2, 3];
+        let cells = Cell::from_mut(&mut v[..]).as_slice_of_cells();
+        cells[0].swap(&cells[2]);
+        cells[1].set(cells[0].get() + cells[2].get());
+        assert_eq!(v, [3, 4, 1]);
+
+        let mut c = Cell::new(5);
+        *c.get_mut() += 1;
+        assert_eq!(c.get(), 6);
+    }
+
+    #[test]
+    fn std_traits() {
+        let a = Cell::new(1);
+        let b = a.clone();
+        b.set(2);
+        assert!(a < b);
+        assert_eq!(a, Cell::from(1));
+        assert_eq!(Cell::<u8>::default().get(), 0);
+        assert_eq!(format!("{:?}", a), "Cell { value: 1 }");
+    }
 }
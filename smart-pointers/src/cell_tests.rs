@@ -1,69 +1,154 @@
-// these aren't _quite_ functional tests,
-// and should all be compile_fail,
-// but may be illustrative
+// drop and borrow-state behavior of Rc and RefCell, across the whole API.
+// these should also pass under miri: `cargo +nightly miri test`.
+// the things that must not compile live in compile_fail.rs.
+
+use crate::cell::Cell;
+use crate::rc::{Rc, Weak};
+use crate::refcell::{Ref, RefCell, RefMut};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+// counts how often it has been dropped.
+struct DropCounter<'a>(&'a Cell<usize>);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
 
 #[test]
-fn concurrent_set() {
-    use std::sync::Arc;
-    let x = Arc::new(Cell::new(42));
-    let x1 = Arc::clone(&x);
-    std::thread::spawn(move || {
-        x1.set(43);
-    });
-    let x2 = Arc::clone(&x);
-    std::thread::spawn(move || {
-        x2.set(44);
-    });
+fn rc_drops_value_exactly_once() {
+    let drops = Cell::new(0);
+    let a = Rc::new(DropCounter(&drops));
+    let b = Rc::clone(&a);
+    let c = Rc::clone(&b);
+    drop(a);
+    drop(c);
+    assert_eq!(drops.get(), 0);
+    drop(b);
+    assert_eq!(drops.get(), 1);
 }
 
 #[test]
-fn set_during_get() {
-    let x = Cell::new(String::from("hello"));
-    let first = x.get();
-    x.set(String::new());
-    x.set(String::from("world"));
-    eprintln!("{}", first);
+fn rc_slice_drops_every_element() {
+    let drops = Cell::new(0);
+    let slice: Rc<[DropCounter]> = (0..4).map(|_| DropCounter(&drops)).collect();
+    let weak = Rc::downgrade(&slice);
+    drop(slice);
+    assert_eq!(drops.get(), 4);
+    assert!(weak.upgrade().is_none());
 }
 
 #[test]
-fn concurrent_set_take2() {
-    use std::sync::Arc;
-    let x = Arc::new(Cell::new([0; 40240]));
-    let x1 = Arc::clone(&x);
-    let jh1 = std::thread::spawn(move || {
-        x1.set([1; 40240]);
+fn weak_breaks_parent_child_cycle() {
+    struct Node<'a> {
+        parent: RefCell<Weak<Node<'a>>>,
+        children: RefCell<Vec<Rc<Node<'a>>>>,
+        _counter: DropCounter<'a>,
+    }
+
+    let drops = Cell::new(0);
+    let parent = Rc::new(Node {
+        parent: RefCell::new(Weak::new()),
+        children: RefCell::new(Vec::new()),
+        _counter: DropCounter(&drops),
     });
-    let x2 = Arc::clone(&x);
-    let jh2 = std::thread::spawn(move || {
-        x2.set([2; 40240]);
+    let child = Rc::new(Node {
+        parent: RefCell::new(Rc::downgrade(&parent)),
+        children: RefCell::new(Vec::new()),
+        _counter: DropCounter(&drops),
     });
-    jh1.join().unwrap();
-    jh2.join().unwrap();
-    let xs = x.get();
-    for &i in xs.iter() {
-        eprintln!("{}", i);
+    parent.children.borrow_mut().push(Rc::clone(&child));
+
+    assert!(parent.parent.borrow().upgrade().is_none());
+    assert!(child.children.borrow().is_empty());
+    let up = child.parent.borrow().upgrade().unwrap();
+    assert!(Rc::ptr_eq(&up, &parent));
+    drop(up);
+    assert_eq!(Rc::strong_count(&child), 2);
+    assert_eq!(Rc::weak_count(&parent), 1);
+
+    drop(parent);
+    assert_eq!(drops.get(), 1);
+    assert!(child.parent.borrow().upgrade().is_none());
+    drop(child);
+    assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn rc_refcell_shared_mutation() {
+    let shared = Rc::new(RefCell::new(Vec::new()));
+    let other = Rc::clone(&shared);
+    shared.borrow_mut().push(1);
+    other.borrow_mut().push(2);
+    {
+        let a = shared.borrow();
+        let b = other.borrow();
+        assert_eq!(*a, *b);
+        assert!(other.try_borrow_mut().is_err());
     }
+    assert_eq!(*shared.borrow(), [1, 2]);
 }
 
 #[test]
-fn concurrent_get_set() {
-    use std::sync::Arc;
-    let x = Arc::new(Cell::new(0));
-    let x1 = Arc::clone(&x);
-    let jh1 = std::thread::spawn(move || {
-        for _ in 0..1000000 {
-            let x = x1.get();
-            x1.set(x + 1);
-        }
-    });
-    let x2 = Arc::clone(&x);
-    let jh2 = std::thread::spawn(move || {
-        for _ in 0..1000000 {
-            let x = x2.get();
-            x2.set(x + 1);
-        }
-    });
-    jh1.join().unwrap();
-    jh2.join().unwrap();
-    assert_eq!(x.get(), 2000000);
+fn refcell_recovers_after_panic_while_borrowed() {
+    let cell = RefCell::new(vec![1]);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut w = cell.borrow_mut();
+        w.push(2);
+        panic!("while holding a RefMut");
+    }));
+    assert!(result.is_err());
+    // the RefMut was dropped during unwinding, which released the borrow.
+    assert_eq!(*cell.borrow_mut(), [1, 2]);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let _r = cell.borrow();
+        let _r2 = Ref::map(cell.borrow(), |v| &v[0]);
+        panic!("while holding Refs");
+    }));
+    assert!(result.is_err());
+    assert!(cell.try_borrow_mut().is_ok());
+}
+
+#[test]
+fn refcell_recovers_after_panic_in_map() {
+    let cell = RefCell::new(vec![1, 2]);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        RefMut::map(cell.borrow_mut(), |v| &mut v[5]);
+    }));
+    assert!(result.is_err());
+    assert!(cell.try_borrow_mut().is_ok());
+}
+
+#[test]
+fn refcell_drops_its_value() {
+    let drops = Cell::new(0);
+    let cell = RefCell::new(DropCounter(&drops));
+    {
+        let _r = cell.borrow();
+    }
+    assert_eq!(drops.get(), 0);
+    drop(cell);
+    assert_eq!(drops.get(), 1);
+}
+
+#[test]
+fn borrow_states_follow_guards() {
+    let cell = RefCell::new((String::from("a"), String::from("b")));
+
+    let r1 = cell.borrow();
+    let r2 = Ref::clone(&r1);
+    drop(r1);
+    assert!(cell.try_borrow_mut().is_err());
+    drop(r2);
+
+    let (mut a, b) = RefMut::map_split(cell.borrow_mut(), |(a, b)| (a, b));
+    a.push_str(&b);
+    assert!(cell.try_borrow().is_err());
+    drop(b);
+    assert!(cell.try_borrow().is_err());
+    drop(a);
+
+    assert_eq!(cell.borrow().0, "ab");
 }
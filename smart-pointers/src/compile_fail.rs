@@ -0,0 +1,143 @@
+//! Things that must not compile, checked by `cargo test --doc`.
+//!
+//! `Cell`, `RefCell`, `OnceCell` and `Rc` are only sound because they can't be shared
+//! between threads, and `Rc` can't even be sent to another one.
+//!
+//! Setting a `Cell` from two threads at once would be a data race:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::cell::Cell;
+//! use std::sync::Arc;
+//!
+//! let x = Arc::new(Cell::new(42));
+//! let x1 = Arc::clone(&x);
+//! std::thread::spawn(move || {
+//!     x1.set(43);
+//! });
+//! let x2 = Arc::clone(&x);
+//! std::thread::spawn(move || {
+//!     x2.set(44);
+//! });
+//! ```
+//!
+//! Even for values that are too large to be written in one go, which could tear:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::cell::Cell;
+//! use std::sync::Arc;
+//!
+//! let x = Arc::new(Cell::new([0; 40240]));
+//! let x1 = Arc::clone(&x);
+//! let jh1 = std::thread::spawn(move || {
+//!     x1.set([1; 40240]);
+//! });
+//! let x2 = Arc::clone(&x);
+//! let jh2 = std::thread::spawn(move || {
+//!     x2.set([2; 40240]);
+//! });
+//! jh1.join().unwrap();
+//! jh2.join().unwrap();
+//! ```
+//!
+//! A read-modify-write from two threads would lose updates:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::cell::Cell;
+//! use std::sync::Arc;
+//!
+//! let x = Arc::new(Cell::new(0));
+//! let x1 = Arc::clone(&x);
+//! let jh1 = std::thread::spawn(move || {
+//!     for _ in 0..1000000 {
+//!         let x = x1.get();
+//!         x1.set(x + 1);
+//!     }
+//! });
+//! let x2 = Arc::clone(&x);
+//! let jh2 = std::thread::spawn(move || {
+//!     for _ in 0..1000000 {
+//!         let x = x2.get();
+//!         x2.set(x + 1);
+//!     }
+//! });
+//! jh1.join().unwrap();
+//! jh2.join().unwrap();
+//! assert_eq!(x.get(), 2000000);
+//! ```
+//!
+//! `Cell::get` copies the value out, since a reference into the cell would be invalidated
+//! by the next `set`:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::cell::Cell;
+//!
+//! let x = Cell::new(String::from("hello"));
+//! let first = x.get();
+//! x.set(String::new());
+//! x.set(String::from("world"));
+//! eprintln!("{}", first);
+//! ```
+//!
+//! `RefCell` and `OnceCell` track their state with plain `Cell`s, so they aren't `Sync` either:
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<smart_pointers::refcell::RefCell<i32>>();
+//! ```
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<smart_pointers::once_cell::OnceCell<i32>>();
+//! ```
+//!
+//! Two clones of an `Rc` on different threads would race on the reference count:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::rc::Rc;
+//!
+//! let rc = Rc::new(5);
+//! let rc2 = Rc::clone(&rc);
+//! std::thread::spawn(move || drop(rc2));
+//! ```
+//!
+//! ```compile_fail,E0277
+//! fn assert_sync<T: Sync>() {}
+//! assert_sync::<smart_pointers::rc::Rc<i32>>();
+//! ```
+//!
+//! ```compile_fail,E0277
+//! fn assert_send<T: Send>() {}
+//! assert_send::<smart_pointers::rc::Weak<i32>>();
+//! ```
+//!
+//! A `Ref` can't outlive its `RefCell`:
+//!
+//! ```compile_fail,E0597
+//! use smart_pointers::refcell::RefCell;
+//!
+//! let r = {
+//!     let cell = RefCell::new(5);
+//!     cell.borrow()
+//! };
+//! ```
+//!
+//! A pinned `Rc` doesn't give its value back, since that would move it:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::rc::Rc;
+//! use std::marker::PhantomPinned;
+//! use std::pin::Pin;
+//!
+//! let rc = Pin::into_inner(Rc::pin(PhantomPinned));
+//! let value = Rc::try_unwrap(rc);
+//! ```
+//!
+//! And `Arc` only crosses threads if its contents may:
+//!
+//! ```compile_fail,E0277
+//! use smart_pointers::cell::Cell;
+//! use smart_pointers::sync::Arc;
+//!
+//! let x = Arc::new(Cell::new(0));
+//! std::thread::spawn(move || x.set(1));
+//! ```
@@ -0,0 +1,484 @@
+//! A reference counted pointer that also reclaims cycles.
+//!
+//! `Gc` works like [`Rc`](crate::rc::Rc), but every allocation is registered in a per-thread heap.
+//! [`collect_cycles`] finds the allocations that are only kept alive by references from
+//! within the heap (trial deletion), and frees them.
+
+use crate::cell::Cell;
+use crate::refcell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut};
+use std::alloc::{dealloc, Layout};
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+
+/// Reports the `Gc`s a value owns to the cycle collector.
+///
+/// Implement it by calling `trace` on every field that (transitively) contains a `Gc`.
+///
+/// # Safety
+///
+/// `trace` must visit every `Gc` owned by the value at most once, and must not visit any `Gc`
+/// it doesn't own: otherwise the collector may free values that are still in use.
+/// Visiting fewer `Gc`s is fine, it only leaks the cycles through them.
+/// It must not create, clone or drop any `Gc`.
+pub unsafe trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+/// Passed to [`Trace::trace`] to visit the `Gc`s of a value.
+pub struct Tracer {
+    phase: Phase,
+    // in Phase::MarkReachable, the reachable allocations whose children we haven't visited yet.
+    worklist: Vec<NonNull<GcBox<dyn Trace>>>,
+}
+
+enum Phase {
+    // every visit is a reference from within the heap, which we subtract from the count.
+    SubtractInternal,
+    // everything visited from a reachable allocation is reachable as well.
+    MarkReachable,
+}
+
+impl Tracer {
+    pub fn visit<T: Trace + 'static>(&mut self, gc: &Gc<T>) {
+        let header = gc.header();
+        match self.phase {
+            Phase::SubtractInternal => header.gc_refs.set(header.gc_refs.get() - 1),
+            Phase::MarkReachable => {
+                if header.gc_refs.get() == 0 {
+                    header.gc_refs.set(1);
+                    self.worklist.push(gc.inner);
+                }
+            }
+        }
+    }
+}
+
+struct GcHeader {
+    strong: Cell<usize>,
+    // scratch space for the collector: references from outside the heap, or 0 if unreachable.
+    gc_refs: Cell<usize>,
+    // position in the heap, so we can unregister in constant time.
+    index: Cell<usize>,
+    // the value has been (or is being) dropped by the collector.
+    dead: Cell<bool>,
+}
+
+struct GcBox<T: ?Sized> {
+    header: GcHeader,
+    value: T,
+}
+
+impl<T: ?Sized> GcBox<T> {
+    // only creates a reference to the header, since the value may be in the middle of being
+    // dropped (when a Gc in a cycle drops a Gc to its own allocation).
+    unsafe fn header<'a>(this: NonNull<Self>) -> &'a GcHeader {
+        &*ptr::addr_of!((*this.as_ptr()).header)
+    }
+
+    unsafe fn value<'a>(this: NonNull<Self>) -> &'a T {
+        &*ptr::addr_of!((*this.as_ptr()).value)
+    }
+}
+
+thread_local! {
+    // all live allocations of this thread.
+    static HEAP: RefCell<Vec<NonNull<GcBox<dyn Trace>>>> = RefCell::new(Vec::new());
+}
+
+fn register(ptr: NonNull<GcBox<dyn Trace>>) {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // SAFETY: the allocation was just created.
+        unsafe { GcBox::header(ptr) }.index.set(heap.len());
+        heap.push(ptr);
+    });
+}
+
+fn unregister(ptr: NonNull<GcBox<dyn Trace>>) {
+    // the heap may already be gone if we're dropped during thread teardown,
+    // in which case there is nothing to unregister from.
+    let _ = HEAP.try_with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // SAFETY: registered allocations are alive.
+        let index = unsafe { GcBox::header(ptr) }.index.get();
+        heap.swap_remove(index);
+        if let Some(&moved) = heap.get(index) {
+            unsafe { GcBox::header(moved) }.index.set(index);
+        }
+    });
+}
+
+/// A single-threaded reference counted pointer whose cycles can be collected.
+pub struct Gc<T: Trace + 'static> {
+    inner: NonNull<GcBox<T>>,
+    _marker: PhantomData<GcBox<T>>,
+}
+
+impl<T: Trace + 'static> Gc<T> {
+    pub fn new(value: T) -> Self {
+        let inner = NonNull::from(Box::leak(Box::new(GcBox {
+            header: GcHeader {
+                strong: Cell::new(1),
+                gc_refs: Cell::new(0),
+                index: Cell::new(0),
+                dead: Cell::new(false),
+            },
+            value,
+        })));
+        register(inner);
+        Gc {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    fn header(&self) -> &GcHeader {
+        // SAFETY: the allocation is only freed once all Gcs are gone (or by the collector,
+        // but only once it has made sure there are no Gcs left).
+        unsafe { GcBox::header(self.inner) }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().strong.get()
+    }
+
+    /// Whether both `Gc`s point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner == other.inner
+    }
+}
+
+impl<T: Trace + 'static> std::ops::Deref for Gc<T> {
+    type Target = T;
+    /// # Panics
+    ///
+    /// Panics if the value has been dropped by the collector, which can only be observed
+    /// from the `Drop` impl of another value in the same garbage cycle.
+    fn deref(&self) -> &T {
+        assert!(
+            !self.header().dead.get(),
+            "Gc dereferenced after its value was collected"
+        );
+        // SAFETY: the allocation is alive (see header), and the value hasn't been dropped:
+        // the collector marks values dead before dropping them, and otherwise values are only
+        // dropped once there are no Gcs left.
+        unsafe { GcBox::value(self.inner) }
+    }
+}
+
+impl<T: Trace + 'static> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        let header = self.header();
+        header.strong.set(header.strong.get() + 1);
+        Gc {
+            inner: self.inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Trace + 'static> Drop for Gc<T> {
+    fn drop(&mut self) {
+        let header = self.header();
+        header.strong.set(header.strong.get() - 1);
+        if header.strong.get() != 0 {
+            return;
+        }
+
+        // the collector has already dropped the value and unregistered it,
+        // we only have to free the memory.
+        if !header.dead.get() {
+            unregister(self.inner);
+            // SAFETY: we were the last Gc, so nobody else can reach the value.
+            unsafe { ptr::drop_in_place(ptr::addr_of_mut!((*self.inner.as_ptr()).value)) };
+        }
+        // SAFETY: the value is gone, and so is every Gc. the memory was allocated by Box.
+        unsafe { dealloc(self.inner.as_ptr() as *mut u8, Layout::new::<GcBox<T>>()) };
+    }
+}
+
+unsafe impl<T: Trace + 'static> Trace for Gc<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.visit(self);
+    }
+}
+
+/// Frees all values of this thread that are only reachable through cycles of `Gc`s.
+/// Returns how many values were freed.
+///
+/// The `Drop` impls of the freed values must not dereference `Gc`s, since the values
+/// they point to might have been dropped already.
+pub fn collect_cycles() -> usize {
+    // a snapshot, so we don't hold on to the heap while running user code.
+    let boxes = HEAP.with(|heap| heap.borrow().clone());
+
+    // SAFETY: all registered allocations are alive, and Trace impls don't drop any Gcs,
+    // so they stay alive until we drop values ourselves.
+    unsafe {
+        for b in &boxes {
+            let header = GcBox::header(*b);
+            header.gc_refs.set(header.strong.get());
+        }
+
+        // whatever is left is the number of references from outside the heap.
+        let mut tracer = Tracer {
+            phase: Phase::SubtractInternal,
+            worklist: Vec::new(),
+        };
+        for b in &boxes {
+            GcBox::value(*b).trace(&mut tracer);
+        }
+
+        // everything with outside references is reachable, and so is everything it points to.
+        tracer.phase = Phase::MarkReachable;
+        tracer.worklist = boxes
+            .iter()
+            .copied()
+            .filter(|&b| GcBox::header(b).gc_refs.get() > 0)
+            .collect();
+        while let Some(b) = tracer.worklist.pop() {
+            GcBox::value(b).trace(&mut tracer);
+        }
+
+        // the layouts are taken now, since the values are gone by the time we free them.
+        let garbage: Vec<_> = boxes
+            .into_iter()
+            .filter(|&b| GcBox::header(b).gc_refs.get() == 0)
+            .map(|b| (b, Layout::for_value(b.as_ref())))
+            .collect();
+
+        // keep all garbage allocations alive while we drop their values,
+        // which drops the Gcs between them.
+        for &(b, _) in &garbage {
+            let header = GcBox::header(b);
+            header.dead.set(true);
+            header.strong.set(header.strong.get() + 1);
+            unregister(b);
+        }
+        for &(b, _) in &garbage {
+            ptr::drop_in_place(ptr::addr_of_mut!((*b.as_ptr()).value));
+        }
+        for &(b, layout) in &garbage {
+            let header = GcBox::header(b);
+            header.strong.set(header.strong.get() - 1);
+            // if a Drop impl stashed away a Gc, the memory is freed once that one is dropped.
+            if header.strong.get() == 0 {
+                dealloc(b.as_ptr() as *mut u8, layout);
+            }
+        }
+
+        garbage.len()
+    }
+}
+
+/// A [`RefCell`] for use inside of `Gc`s, so that cycles can be formed.
+pub struct GcCell<T> {
+    cell: RefCell<T>,
+}
+
+impl<T> GcCell<T> {
+    pub fn new(value: T) -> Self {
+        GcCell {
+            cell: RefCell::new(value),
+        }
+    }
+
+    #[track_caller]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.cell.borrow()
+    }
+
+    #[track_caller]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.cell.borrow_mut()
+    }
+
+    #[track_caller]
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
+        self.cell.try_borrow()
+    }
+
+    #[track_caller]
+    pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        self.cell.try_borrow_mut()
+    }
+}
+
+unsafe impl<T: Trace> Trace for GcCell<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        // if the value is mutably borrowed, we can't look at its Gcs. skipping them is fine:
+        // they then look referenced from outside, so they (and their cycles) are kept alive.
+        if let Ok(value) = self.cell.try_borrow() {
+            value.trace(tracer);
+        }
+    }
+}
+
+macro_rules! empty_trace {
+    ($($t:ty),*) => {
+        $(
+            unsafe impl Trace for $t {
+                fn trace(&self, _tracer: &mut Tracer) {}
+            }
+        )*
+    };
+}
+
+empty_trace!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    String,
+    &'static str
+);
+
+unsafe impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(value) = self {
+            value.trace(tracer);
+        }
+    }
+}
+
+unsafe impl<T: Trace + ?Sized> Trace for Box<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        (**self).trace(tracer);
+    }
+}
+
+unsafe impl<T: Trace> Trace for [T] {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self {
+            value.trace(tracer);
+        }
+    }
+}
+
+unsafe impl<T: Trace, const N: usize> Trace for [T; N] {
+    fn trace(&self, tracer: &mut Tracer) {
+        self[..].trace(tracer);
+    }
+}
+
+unsafe impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        self[..].trace(tracer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::Rc;
+
+    struct Node {
+        edges: GcCell<Vec<Gc<Node>>>,
+        drops: Rc<Cell<usize>>,
+    }
+
+    impl Node {
+        fn new(drops: &Rc<Cell<usize>>) -> Gc<Node> {
+            Gc::new(Node {
+                edges: GcCell::new(Vec::new()),
+                drops: Rc::clone(drops),
+            })
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            self.drops.set(self.drops.get() + 1);
+        }
+    }
+
+    unsafe impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.edges.trace(tracer);
+        }
+    }
+
+    #[test]
+    fn collects_unreachable_cycle() {
+        let drops = Rc::new(Cell::new(0));
+        let a = Node::new(&drops);
+        let b = Node::new(&drops);
+        a.edges.borrow_mut().push(b.clone());
+        b.edges.borrow_mut().push(a.clone());
+        // a self-cycle as well.
+        a.edges.borrow_mut().push(a.clone());
+
+        assert_eq!(collect_cycles(), 0);
+        drop(a);
+        assert_eq!(drops.get(), 0);
+        assert_eq!(collect_cycles(), 0);
+
+        drop(b);
+        assert_eq!(drops.get(), 0);
+        assert_eq!(collect_cycles(), 2);
+        assert_eq!(drops.get(), 2);
+    }
+
+    #[test]
+    fn keeps_cycles_reachable_from_outside() {
+        let drops = Rc::new(Cell::new(0));
+        let root = Node::new(&drops);
+        let a = Node::new(&drops);
+        let b = Node::new(&drops);
+        root.edges.borrow_mut().push(a.clone());
+        a.edges.borrow_mut().push(b.clone());
+        b.edges.borrow_mut().push(a.clone());
+        drop((a, b));
+
+        assert_eq!(collect_cycles(), 0);
+        let a = root.edges.borrow()[0].clone();
+        assert_eq!(Gc::strong_count(&a), 3);
+
+        drop(a);
+        drop(root);
+        assert_eq!(drops.get(), 1);
+        assert_eq!(collect_cycles(), 2);
+        assert_eq!(drops.get(), 3);
+    }
+
+    #[test]
+    fn acyclic_values_are_freed_without_collection() {
+        let drops = Rc::new(Cell::new(0));
+        let a = Node::new(&drops);
+        a.edges.borrow_mut().push(Node::new(&drops));
+        drop(a);
+        assert_eq!(drops.get(), 2);
+        assert_eq!(collect_cycles(), 0);
+    }
+
+    #[test]
+    fn collects_while_cell_is_borrowed() {
+        let drops = Rc::new(Cell::new(0));
+        let a = Node::new(&drops);
+        let b = Node::new(&drops);
+        a.edges.borrow_mut().push(b.clone());
+        b.edges.borrow_mut().push(a.clone());
+        drop(a);
+
+        // the collector can't look into b's edges, and must not panic or free anything.
+        let guard = b.edges.borrow_mut();
+        assert_eq!(collect_cycles(), 0);
+        drop(guard);
+        drop(b);
+        assert_eq!(collect_cycles(), 2);
+        assert_eq!(drops.get(), 2);
+    }
+}
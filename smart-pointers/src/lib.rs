@@ -1,4 +1,15 @@
-//mod cell_tests; illustrative tests
-mod cell;
-mod rc;
-mod refcell;
+#![cfg_attr(feature = "nightly", feature(coerce_unsized, unsize))]
+pub mod alloc;
+pub mod cell;
+pub mod gc;
+pub mod once_cell;
+pub mod rc;
+pub mod refcell;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod sync;
+
+#[cfg(test)]
+mod cell_tests;
+#[cfg(doctest)]
+mod compile_fail;
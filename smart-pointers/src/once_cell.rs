@@ -0,0 +1,223 @@
+use crate::cell::Cell;
+use std::cell::UnsafeCell;
+use std::ops::Deref;
+
+/// A cell that can be written to only once.
+///
+/// Unlike [`RefCell`](crate::refcell::RefCell), it hands out plain `&T`s, since the value
+/// never changes after it has been set.
+pub struct OnceCell<T> {
+    value: UnsafeCell<Option<T>>,
+    // set while an initializer runs, to catch it trying to initialize the same cell again.
+    initializing: Cell<bool>,
+}
+
+// implied by UnsafeCell
+// impl<T> !Sync for OnceCell<T> {}
+
+impl<T> OnceCell<T> {
+    pub fn new() -> Self {
+        OnceCell {
+            value: UnsafeCell::new(None),
+            initializing: Cell::new(false),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: the value is only ever written once, while it is None, and we only hand out
+        // references once it is Some. so this doesn't alias with any mutable reference.
+        unsafe { &*self.value.get() }.as_ref()
+    }
+
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.value.get_mut().as_mut()
+    }
+
+    /// Sets the value, or gives it back if the cell is already initialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within the initializer of this cell.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if self.get().is_some() {
+            return Err(value);
+        }
+        self.assert_not_initializing();
+        // SAFETY: the value is None, so no references to it have been handed out,
+        // and nobody else is writing it, since we are !Sync and not within an initializer.
+        unsafe { *self.value.get() = Some(value) };
+        Ok(())
+    }
+
+    /// Returns the value, initializing it with `f` if the cell is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` tries to initialize this cell itself. If `f` panics, the panic is
+    /// propagated and the cell stays empty.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        match self.get_or_try_init(|| Ok::<T, std::convert::Infallible>(f())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns the value, initializing it with `f` if the cell is empty.
+    /// If `f` fails, the error is returned and the cell stays empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` tries to initialize this cell itself.
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        self.assert_not_initializing();
+        let value = {
+            let _guard = InitGuard::new(&self.initializing);
+            f()?
+        };
+
+        // SAFETY: the value is still None: the initializer couldn't have set it,
+        // since that would have panicked on reentrancy.
+        unsafe { *self.value.get() = Some(value) };
+        Ok(self.get().expect("just initialized"))
+    }
+
+    pub fn take(&mut self) -> Option<T> {
+        self.value.get_mut().take()
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    fn assert_not_initializing(&self) {
+        if self.initializing.get() {
+            panic!("reentrant initialization of OnceCell");
+        }
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// marks a cell as initializing, and unmarks it again, even if the initializer panics.
+struct InitGuard<'a> {
+    initializing: &'a Cell<bool>,
+}
+
+impl<'a> InitGuard<'a> {
+    fn new(initializing: &'a Cell<bool>) -> Self {
+        initializing.set(true);
+        InitGuard { initializing }
+    }
+}
+
+impl Drop for InitGuard<'_> {
+    fn drop(&mut self) {
+        self.initializing.set(false);
+    }
+}
+
+/// A value that is computed on first access.
+pub struct Lazy<T, F = fn() -> T> {
+    cell: OnceCell<T>,
+    // taken out when the value is computed. None afterwards, or if the initializer panicked.
+    init: Cell<Option<F>>,
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    pub fn new(init: F) -> Self {
+        Lazy {
+            cell: OnceCell::new(),
+            init: Cell::new(Some(init)),
+        }
+    }
+
+    /// Computes the value if it hasn't been computed yet, and returns it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the initializer panicked on an earlier access, or if it tries to access
+    /// this `Lazy` itself.
+    pub fn force(this: &Self) -> &T {
+        this.cell.get_or_init(|| match this.init.take() {
+            Some(f) => f(),
+            None => panic!("Lazy instance has previously been poisoned"),
+        })
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Lazy::new(T::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_once() {
+        let cell = OnceCell::new();
+        assert!(cell.get().is_none());
+        assert_eq!(cell.set(1), Ok(()));
+        assert_eq!(cell.set(2), Err(2));
+        assert_eq!(cell.get_or_init(|| 3), &1);
+    }
+
+    #[test]
+    fn failed_init_leaves_cell_empty() {
+        let cell = OnceCell::new();
+        assert_eq!(cell.get_or_try_init(|| Err("nope")), Err("nope"));
+        assert!(cell.get().is_none());
+        assert_eq!(cell.get_or_try_init(|| Ok::<_, ()>(5)), Ok(&5));
+
+        let cell = OnceCell::<i32>::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            cell.get_or_init(|| panic!("boom"));
+        }));
+        assert!(result.is_err());
+        // the reentrancy flag has been reset, so this is not mistaken for reentrancy.
+        assert_eq!(cell.get_or_init(|| 7), &7);
+    }
+
+    #[test]
+    #[should_panic(expected = "reentrant initialization of OnceCell")]
+    fn reentrant_init() {
+        let cell = OnceCell::new();
+        cell.get_or_init(|| *cell.get_or_init(|| 1) + 1);
+    }
+
+    #[test]
+    fn lazy_runs_once() {
+        let calls = Cell::new(0);
+        let lazy = Lazy::new(|| {
+            calls.set(calls.get() + 1);
+            String::from("computed")
+        });
+        assert_eq!(calls.get(), 0);
+        assert_eq!(&*lazy, "computed");
+        assert_eq!(lazy.len(), 8);
+        assert_eq!(calls.get(), 1);
+    }
+}
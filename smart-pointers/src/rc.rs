@@ -1,66 +1,971 @@
+use crate::alloc::{Allocator, Global};
 use crate::cell::Cell;
+use std::alloc::{handle_alloc_error, Layout};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::marker::PhantomData;
-use std::ptr::NonNull;
+use std::mem::ManuallyDrop;
+use std::pin::Pin;
+use std::ptr::{self, NonNull};
 
-struct RcInner<T> {
+// repr(C) so that the counts come first, and the (possibly unsized) value sits at the offset
+// we get from extending the layout of the counts with the layout of the value.
+#[repr(C)]
+struct RcInner<T: ?Sized> {
+    // number of Rcs pointing at this allocation.
+    strong: Cell<usize>,
+    // number of Weaks pointing at this allocation, plus one held collectively by all Rcs.
+    weak: Cell<usize>,
     value: T,
-    refcount: Cell<usize>,
 }
 
-pub struct Rc<T> {
+impl<T: ?Sized> RcInner<T> {
+    fn inc_strong(&self) {
+        self.strong.set(self.strong.get() + 1);
+    }
+
+    fn dec_strong(&self) {
+        self.strong.set(self.strong.get() - 1);
+    }
+
+    fn inc_weak(&self) {
+        self.weak.set(self.weak.get() + 1);
+    }
+
+    fn dec_weak(&self) {
+        self.weak.set(self.weak.get() - 1);
+    }
+}
+
+/// A single-threaded reference-counted pointer, allocated in `A`.
+pub struct Rc<T: ?Sized, A: Allocator = Global> {
     inner: NonNull<RcInner<T>>,
+    // every Rc and Weak of an allocation holds a clone of the allocator it came from.
+    alloc: A,
     _marker: PhantomData<RcInner<T>>,
 }
 
+#[cfg(feature = "nightly")]
+impl<T: ?Sized + std::marker::Unsize<U>, U: ?Sized, A: Allocator> std::ops::CoerceUnsized<Rc<U, A>>
+    for Rc<T, A>
+{
+}
+
 impl<T> Rc<T> {
     pub fn new(v: T) -> Self {
-        let inner = Box::new(RcInner {
-            value: v,
-            refcount: Cell::new(1),
+        Rc::new_in(v, Global)
+    }
+
+    /// Creates a pinned `Rc`.
+    ///
+    /// The value lives in the allocation until it is dropped, no matter how the `Rc`s to it
+    /// are moved around or cloned, and none of the methods that could move it out are
+    /// reachable through a `Pin<Rc<T>>`:
+    ///
+    /// ```
+    /// use smart_pointers::cell::Cell;
+    /// use smart_pointers::rc::Rc;
+    /// use std::marker::PhantomPinned;
+    /// use std::ptr;
+    ///
+    /// // remembers where it lives, which is only sound as long as it doesn't move.
+    /// struct SelfRef {
+    ///     addr: Cell<*const SelfRef>,
+    ///     _pin: PhantomPinned,
+    /// }
+    ///
+    /// let pinned = Rc::pin(SelfRef {
+    ///     addr: Cell::new(ptr::null()),
+    ///     _pin: PhantomPinned,
+    /// });
+    /// pinned.addr.set(&*pinned);
+    ///
+    /// let handles = vec![pinned.clone(), pinned];
+    /// for handle in &handles {
+    ///     assert!(ptr::eq(handle.addr.get(), &**handle));
+    /// }
+    /// ```
+    ///
+    /// Its pinned fields can be reached through [`Rc::project`].
+    pub fn pin(v: T) -> Pin<Rc<T>> {
+        Rc::pin_in(v, Global)
+    }
+}
+
+impl<T, A: Allocator> Rc<T, A> {
+    /// Like [`Rc::new`], but places the value in `alloc`.
+    pub fn new_in(v: T, alloc: A) -> Self {
+        unsafe {
+            let inner =
+                Self::allocate_for_layout(Layout::new::<T>(), &alloc, |mem| mem as *mut RcInner<T>);
+            ptr::addr_of_mut!((*inner.as_ptr()).value).write(v);
+            Self::from_inner_in(inner, alloc)
+        }
+    }
+
+    /// Like [`Rc::pin`], but places the value in `alloc`.
+    ///
+    /// The allocator has to be `'static`, since a borrowed one could free the memory of a leaked
+    /// `Rc` without dropping the value, which pinning rules out.
+    pub fn pin_in(v: T, alloc: A) -> Pin<Self>
+    where
+        A: 'static,
+    {
+        // SAFETY: the value is never moved out of the allocation, see Rc::pin.
+        unsafe { Pin::new_unchecked(Rc::new_in(v, alloc)) }
+    }
+
+    /// Returns the inner value if this is the only `Rc`, otherwise gives the `Rc` back.
+    ///
+    /// Outstanding `Weak`s don't prevent this, they just won't upgrade anymore.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if Rc::strong_count(&this) != 1 {
+            return Err(this);
+        }
+
+        // SAFETY: we are the only Rc, and we never drop it, so the value and the allocator
+        // are moved out exactly once.
+        let this = ManuallyDrop::new(this);
+        let value = unsafe { ptr::read(&this.inner().value) };
+        let alloc = unsafe { ptr::read(&this.alloc) };
+        this.inner().dec_strong();
+        // release the weak reference held collectively by the Rcs.
+        drop(Weak {
+            inner: this.inner,
+            alloc,
+            _marker: PhantomData,
         });
+        Ok(value)
+    }
+
+    /// Returns the inner value if this is the only `Rc`, otherwise drops this `Rc`.
+    pub fn into_inner(this: Self) -> Option<T> {
+        Rc::try_unwrap(this).ok()
+    }
+
+    /// Returns a mutable reference to the value, cloning it first if it is shared.
+    ///
+    /// If there are other `Rc`s, the value is cloned into a new allocation that only `this`
+    /// points to. If there are only `Weak`s, the value is moved into a new allocation and the
+    /// `Weak`s are disassociated from it.
+    pub fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+        A: Clone,
+    {
+        if Rc::strong_count(this) != 1 {
+            *this = Rc::new_in((**this).clone(), this.alloc.clone());
+        } else if Rc::weak_count(this) != 0 {
+            // this takes over the weak reference held by the Rcs, and releases it once we're done.
+            let _weak = Weak {
+                inner: this.inner,
+                alloc: this.alloc.clone(),
+                _marker: PhantomData,
+            };
+            // with a strong count of zero, the Weaks can never upgrade again.
+            this.inner().dec_strong();
+            // SAFETY: the strong count is zero, so nobody else is going to read or drop the value.
+            // we overwrite `this` without dropping it, since its strong count is already released,
+            // and its allocator moves into the new Rc.
+            unsafe {
+                let value = ptr::read(&this.inner().value);
+                let alloc = ptr::read(&this.alloc);
+                ptr::write(this, Rc::new_in(value, alloc));
+            }
+        }
+
+        // SAFETY: at this point we hold the only Rc and there are no Weaks that can upgrade.
+        unsafe { &mut (*this.inner.as_ptr()).value }
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Rc<T, A> {
+    fn inner(&self) -> &RcInner<T> {
+        // SAFETY: the allocation is only freed once all Rcs and Weaks are gone.
+        // we have an Rc, therefore it has not been freed.
+        unsafe { self.inner.as_ref() }
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T, A>
+    where
+        A: Clone,
+    {
+        this.inner().inc_weak();
+        Weak {
+            inner: this.inner,
+            alloc: this.alloc.clone(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        // don't count the weak reference held by the Rcs.
+        this.inner().weak.get() - 1
+    }
+
+    /// The allocator the value lives in.
+    pub fn allocator(this: &Self) -> &A {
+        &this.alloc
+    }
 
+    /// Returns a mutable reference to the value if there are no other `Rc`s or `Weak`s.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Rc::strong_count(this) == 1 && Rc::weak_count(this) == 0 {
+            // SAFETY: we are the only Rc and there are no Weaks,
+            // and we hold &mut self, so nobody else can access the value.
+            Some(unsafe { &mut (*this.inner.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// Whether both `Rc`s point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        // compare only the addresses, vtables of trait objects aren't guaranteed to be unique.
+        this.inner.as_ptr() as *const () == other.inner.as_ptr() as *const ()
+    }
+
+    /// Like [`Rc::get_mut`], for a pinned `Rc`.
+    pub fn pin_get_mut(this: &mut Pin<Self>) -> Option<Pin<&mut T>> {
+        // SAFETY: Pin is repr(transparent), and we only use the Rc to get at the value,
+        // which we hand out pinned again, so it isn't moved.
+        let rc = unsafe { &mut *(this as *mut Pin<Self> as *mut Self) };
+        Rc::get_mut(rc).map(|value| unsafe { Pin::new_unchecked(value) })
+    }
+
+    /// Projects a pinned `Rc` to a pinned part of its value, keeping the `Rc` alive.
+    ///
+    /// ```
+    /// use smart_pointers::rc::{Projection, Rc};
+    /// use std::future::Future;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// struct Task<F> {
+    ///     id: u32,
+    ///     future: F,
+    /// }
+    ///
+    /// let task = Rc::pin(Task { id: 1, future: async { 5 } });
+    /// // SAFETY: Task never moves its future, so it is pinned whenever the Task is.
+    /// let mut future = unsafe { Rc::project(task, |task| &task.future) };
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// let future = Projection::get_mut(&mut future).unwrap();
+    /// assert_eq!(future.poll(&mut cx), Poll::Ready(5));
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `f` must return a part of the value it is given, not something the value points to.
+    /// The rest is the same as for [`Pin::map_unchecked`]: the part must stay pinned as long as
+    /// the value is, i.e. it must not be moved out of the value, not even by its `Drop` impl.
+    pub unsafe fn project<U: ?Sized>(
+        this: Pin<Self>,
+        f: impl FnOnce(&T) -> &U,
+    ) -> Projection<T, U, A> {
+        let rc = Pin::into_inner_unchecked(this);
+        let value = Rc::as_ptr(&rc);
+        let part: *const U = f(&*rc);
+        // the reference only lets us read, so we point a pointer derived from the allocation at
+        // the part instead. like in From<Box<T>>, we overwrite the data pointer in place, which
+        // keeps the metadata of the part.
+        let offset = (part as *const u8).offset_from(value as *const u8);
+        let mut part = part as *mut U;
+        ptr::write(
+            &mut part as *mut *mut U as *mut *mut u8,
+            (value as *mut u8).offset(offset),
+        );
+        Projection {
+            rc,
+            part: NonNull::new_unchecked(part),
+        }
+    }
+
+    /// Returns a pointer to the value, without affecting the counts.
+    pub fn as_ptr(this: &Self) -> *const T {
+        // SAFETY: the allocation is alive, and we don't create a reference to the value.
+        unsafe { ptr::addr_of!((*this.inner.as_ptr()).value) }
+    }
+
+    // allocates an RcInner with room for a value with the given layout and initializes the counts.
+    // the value itself is left uninitialized. `mem_to_inner` attaches the pointer metadata
+    // (slice length, vtable) to the freshly allocated memory.
+    unsafe fn allocate_for_layout(
+        value_layout: Layout,
+        alloc: &A,
+        mem_to_inner: impl FnOnce(*mut u8) -> *mut RcInner<T>,
+    ) -> NonNull<RcInner<T>> {
+        let layout = Layout::new::<RcInner<()>>()
+            .extend(value_layout)
+            .expect("capacity overflow")
+            .0
+            .pad_to_align();
+
+        let mem = match alloc.allocate(layout) {
+            Ok(mem) => mem.as_ptr(),
+            Err(_) => handle_alloc_error(layout),
+        };
+
+        let inner = mem_to_inner(mem);
+        ptr::addr_of_mut!((*inner).strong).write(Cell::new(1));
+        ptr::addr_of_mut!((*inner).weak).write(Cell::new(1));
+        NonNull::new_unchecked(inner)
+    }
+
+    fn from_inner_in(inner: NonNull<RcInner<T>>, alloc: A) -> Self {
         Rc {
-            // SAFETY: Box does not give us a null pointer.
-            inner: unsafe { NonNull::new_unchecked(Box::into_raw(inner)) },
+            inner,
+            alloc,
             _marker: PhantomData,
         }
     }
 }
 
-impl<T> std::ops::Deref for Rc<T> {
+impl<T: ?Sized> Rc<T> {
+    /// Consumes the `Rc`, returning a pointer to the value.
+    ///
+    /// The strong reference is leaked into the pointer. Use [`Rc::from_raw`] to get it back,
+    /// otherwise the value will never be dropped.
+    pub fn into_raw(this: Self) -> *const T {
+        let ptr = Rc::as_ptr(&this);
+        std::mem::forget(this);
+        ptr
+    }
+
+    /// Reconstructs an `Rc` from a pointer returned by [`Rc::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from [`Rc::into_raw`] of an `Rc<T>`, and every such pointer
+    /// must be converted back at most once.
+    pub unsafe fn from_raw(ptr: *const T) -> Self {
+        // the value sits at a fixed offset behind the counts, determined by its alignment.
+        let offset = Layout::new::<RcInner<()>>()
+            .extend(Layout::for_value(&*ptr))
+            .unwrap()
+            .1;
+        // stepping back keeps the pointer metadata (slice length, vtable).
+        let inner = (ptr as *mut T).wrapping_byte_sub(offset) as *mut RcInner<T>;
+        Self::from_inner(NonNull::new_unchecked(inner))
+    }
+
+    fn from_inner(inner: NonNull<RcInner<T>>) -> Self {
+        Self::from_inner_in(inner, Global)
+    }
+}
+
+impl<T> Rc<[T]> {
+    unsafe fn allocate_for_slice(len: usize) -> NonNull<RcInner<[T]>> {
+        Self::allocate_for_layout(
+            Layout::array::<T>(len).expect("capacity overflow"),
+            &Global,
+            // a slice pointer cast keeps its length, which becomes the length of the value.
+            |mem| ptr::slice_from_raw_parts_mut(mem as *mut T, len) as *mut RcInner<[T]>,
+        )
+    }
+}
+
+impl<T> From<Vec<T>> for Rc<[T]> {
+    fn from(mut v: Vec<T>) -> Self {
+        unsafe {
+            let inner = Self::allocate_for_slice(v.len());
+            let dst = ptr::addr_of_mut!((*inner.as_ptr()).value) as *mut T;
+            ptr::copy_nonoverlapping(v.as_ptr(), dst, v.len());
+            // the elements have been moved into the Rc, so only free the Vec's buffer.
+            v.set_len(0);
+            Self::from_inner(inner)
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Rc<[T]> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        // we need to know the length up front, so collect into a Vec first.
+        iter.into_iter().collect::<Vec<T>>().into()
+    }
+}
+
+impl From<&str> for Rc<str> {
+    fn from(s: &str) -> Self {
+        unsafe {
+            let inner = Rc::<[u8]>::allocate_for_slice(s.len());
+            let dst = ptr::addr_of_mut!((*inner.as_ptr()).value) as *mut u8;
+            ptr::copy_nonoverlapping(s.as_ptr(), dst, s.len());
+            // str has the same layout and metadata as [u8], and the bytes are valid UTF-8.
+            Self::from_inner(NonNull::new_unchecked(inner.as_ptr() as *mut RcInner<str>))
+        }
+    }
+}
+
+impl From<String> for Rc<str> {
+    fn from(s: String) -> Self {
+        Self::from(&s[..])
+    }
+}
+
+/// Moves a boxed value into an `Rc`.
+///
+/// This also works for unsized values, so `Rc<dyn Trait>` can be created from a `Box<dyn Trait>`.
+impl<T: ?Sized, A: Allocator> Rc<T, A> {
+    // moves the value out of the box into a new allocation in `alloc`.
+    pub(crate) fn from_box_in(b: Box<T>, alloc: A) -> Self {
+        unsafe {
+            let value_layout = Layout::for_value(&*b);
+            let box_ptr = Box::into_raw(b);
+            let inner = Self::allocate_for_layout(value_layout, &alloc, |mem| {
+                // point the box pointer at the new allocation, keeping its metadata
+                // (slice length, vtable). the data pointer is the first part of a fat pointer,
+                // so we overwrite it in place, which also carries over the provenance of mem.
+                let mut ptr = box_ptr;
+                ptr::write(&mut ptr as *mut *mut T as *mut *mut u8, mem);
+                ptr as *mut RcInner<T>
+            });
+
+            let dst = ptr::addr_of_mut!((*inner.as_ptr()).value) as *mut u8;
+            ptr::copy_nonoverlapping(box_ptr as *const u8, dst, value_layout.size());
+
+            // the value has been moved into the Rc, so only free the box's memory.
+            Global.deallocate(NonNull::new_unchecked(box_ptr as *mut u8), value_layout);
+            Self::from_inner_in(inner, alloc)
+        }
+    }
+}
+
+impl<T: ?Sized> From<Box<T>> for Rc<T> {
+    fn from(b: Box<T>) -> Self {
+        Rc::from_box_in(b, Global)
+    }
+}
+
+impl<T: ?Sized, A: Allocator> std::ops::Deref for Rc<T, A> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
-        // SAFETY: self.inner is a Box that is only deallocated when the last Rc goes away.
-        // we have an Rc, therefore the Box has not been deallocated, so deref is fine.
-        &unsafe { self.inner.as_ref() }.value
+        // SAFETY: the value is only dropped when the last Rc goes away.
+        // we have an Rc, therefore the value has not been dropped, so deref is fine.
+        &self.inner().value
     }
 }
 
-impl<T> Clone for Rc<T> {
+impl<T: ?Sized, A: Allocator + Clone> Clone for Rc<T, A> {
     fn clone(&self) -> Self {
-        let inner = unsafe { self.inner.as_ref() };
-        let c = inner.refcount.get();
-        inner.refcount.set(c + 1);
+        self.inner().inc_strong();
         Rc {
             inner: self.inner,
+            alloc: self.alloc.clone(),
             _marker: PhantomData,
         }
     }
 }
 
+// moving an Rc doesn't move the value, so it never needs to be pinned itself.
+impl<T: ?Sized, A: Allocator> Unpin for Rc<T, A> {}
+
 // TODO: #[may_dangle]
-impl<T> Drop for Rc<T> {
+impl<T: ?Sized, A: Allocator> Drop for Rc<T, A> {
     fn drop(&mut self) {
-        let inner = unsafe { self.inner.as_ref() };
-        let c = inner.refcount.get();
-        if c == 1 {
-            drop(inner);
-            // SAFETY: we are the _only_ Rc left, and we are being dropped.
+        self.inner().dec_strong();
+        if self.inner().strong.get() == 0 {
+            // SAFETY: we were the _only_ Rc left, and we are being dropped.
             // therefore, after us, there will be no Rc's, and no references to T.
-            let _ = unsafe { Box::from_raw(self.inner.as_ptr()) };
+            // Weaks can't hand out references anymore, since they see a strong count of zero.
+            unsafe { ptr::drop_in_place(&mut (*self.inner.as_ptr()).value) };
+
+            // release the weak reference held collectively by the Rcs.
+            // this frees the allocation if there are no Weaks left.
+            drop(Weak {
+                inner: self.inner,
+                alloc: &self.alloc,
+                _marker: PhantomData,
+            });
+        }
+    }
+}
+
+impl<T: Default> Default for Rc<T> {
+    fn default() -> Self {
+        Rc::new(T::default())
+    }
+}
+
+impl<T> From<T> for Rc<T> {
+    fn from(value: T) -> Self {
+        Rc::new(value)
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Borrow<T> for Rc<T, A> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, A: Allocator> AsRef<T> for Rc<T, A> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+// the comparisons and the hash are those of the values, so that Borrow is consistent.
+// use Rc::ptr_eq to compare identities.
+impl<T: ?Sized + PartialEq, A: Allocator> PartialEq for Rc<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq, A: Allocator> Eq for Rc<T, A> {}
+
+impl<T: ?Sized + PartialOrd, A: Allocator> PartialOrd for Rc<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: ?Sized + Ord, A: Allocator> Ord for Rc<T, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: ?Sized + Hash, A: Allocator> Hash for Rc<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, A: Allocator> fmt::Debug for Rc<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, A: Allocator> fmt::Display for Rc<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Formats the address of the value.
+impl<T: ?Sized, A: Allocator> fmt::Pointer for Rc<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&Rc::as_ptr(self), f)
+    }
+}
+
+/// A pinned part of the value of a `Pin<Rc<T>>`, made by [`Rc::project`].
+///
+/// It holds on to the `Rc`, so the value stays alive and pinned as long as this exists.
+pub struct Projection<T: ?Sized, U: ?Sized, A: Allocator = Global> {
+    // pinned, we just never move the value or hand out a reference to all of it.
+    rc: Rc<T, A>,
+    // into the value of rc.
+    part: NonNull<U>,
+}
+
+impl<T: ?Sized, U: ?Sized, A: Allocator> Projection<T, U, A> {
+    pub fn get(this: &Self) -> Pin<&U> {
+        // SAFETY: rc keeps the part alive, and Rc::project promised that it's pinned.
+        unsafe { Pin::new_unchecked(this.part.as_ref()) }
+    }
+
+    /// Like [`Rc::pin_get_mut`], for the projected part.
+    pub fn get_mut(this: &mut Self) -> Option<Pin<&mut U>> {
+        if Rc::strong_count(&this.rc) == 1 && Rc::weak_count(&this.rc) == 0 {
+            // SAFETY: as in Rc::get_mut, nobody else can access the value. see also get.
+            Some(unsafe { Pin::new_unchecked(this.part.as_mut()) })
+        } else {
+            None
+        }
+    }
+
+    /// Gives back the pinned `Rc` to the whole value.
+    pub fn into_rc(this: Self) -> Pin<Rc<T, A>> {
+        // SAFETY: the value was pinned when we got it, and we didn't move it.
+        unsafe { Pin::new_unchecked(this.rc) }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized, A: Allocator + Clone> Clone for Projection<T, U, A> {
+    fn clone(&self) -> Self {
+        Projection {
+            rc: self.rc.clone(),
+            part: self.part,
+        }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized + fmt::Debug, A: Allocator> fmt::Debug for Projection<T, U, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*Projection::get(self), f)
+    }
+}
+
+/// A non-owning handle to the allocation of an [`Rc`].
+///
+/// A `Weak` keeps the allocation alive, but not the value inside it,
+/// so it can be used to break reference cycles.
+pub struct Weak<T: ?Sized, A: Allocator = Global> {
+    // dangling (usize::MAX) for a Weak created through Weak::new.
+    inner: NonNull<RcInner<T>>,
+    alloc: A,
+    _marker: PhantomData<RcInner<T>>,
+}
+
+#[cfg(feature = "nightly")]
+impl<T: ?Sized + std::marker::Unsize<U>, U: ?Sized, A: Allocator>
+    std::ops::CoerceUnsized<Weak<U, A>> for Weak<T, A>
+{
+}
+
+impl<T: ?Sized, A: Allocator> Unpin for Weak<T, A> {}
+
+impl<T> Weak<T> {
+    /// Creates a `Weak` without an allocation, which never upgrades.
+    pub fn new() -> Self {
+        Weak::new_in(Global)
+    }
+}
+
+impl<T, A: Allocator> Weak<T, A> {
+    /// Like [`Weak::new`], for `Rc`s in `alloc`.
+    pub fn new_in(alloc: A) -> Self {
+        Weak {
+            // SAFETY: usize::MAX is not null.
+            inner: unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(usize::MAX)) },
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Weak<T, A> {
+    fn inner(&self) -> Option<&RcInner<T>> {
+        if self.inner.as_ptr() as *mut () as usize == usize::MAX {
+            None
         } else {
-            // there are other Rcs, so don't drop the Box!
-            inner.refcount.set(c - 1);
+            // SAFETY: the allocation is only freed once all Weaks are gone.
+            // we have a Weak, therefore it has not been freed.
+            // the value might have been dropped, but we only touch the counts.
+            Some(unsafe { &*(self.inner.as_ptr() as *const RcInner<T>) })
+        }
+    }
+
+    pub fn upgrade(&self) -> Option<Rc<T, A>>
+    where
+        A: Clone,
+    {
+        let inner = self.inner()?;
+        if inner.strong.get() == 0 {
+            // the value has already been dropped.
+            None
+        } else {
+            inner.inc_strong();
+            Some(Rc {
+                inner: self.inner,
+                alloc: self.alloc.clone(),
+                _marker: PhantomData,
+            })
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner().map_or(0, |inner| inner.strong.get())
+    }
+
+    pub fn weak_count(&self) -> usize {
+        match self.inner() {
+            // the Rcs still hold their shared weak reference.
+            Some(inner) if inner.strong.get() > 0 => inner.weak.get() - 1,
+            Some(inner) => inner.weak.get(),
+            None => 0,
+        }
+    }
+}
+
+impl<T> Default for Weak<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized, A: Allocator + Clone> Clone for Weak<T, A> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = self.inner() {
+            inner.inc_weak();
+        }
+        Weak {
+            inner: self.inner,
+            alloc: self.alloc.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Drop for Weak<T, A> {
+    fn drop(&mut self) {
+        let inner = match self.inner() {
+            Some(inner) => inner,
+            None => return,
+        };
+        inner.dec_weak();
+        if inner.weak.get() == 0 {
+            // the layout only depends on the pointer metadata, not on the (dropped) value.
+            let layout = Layout::for_value(inner);
+            // SAFETY: there are no Rcs (they hold a weak reference) and no other Weaks left,
+            // so nobody can observe the allocation anymore. the value has already been dropped,
+            // so we only free the memory, which was allocated in this allocator with this layout.
+            unsafe { self.alloc.deallocate(self.inner.cast(), layout) };
+        }
+    }
+}
+
+// the value may already be gone, so there is nothing to print.
+impl<T: ?Sized, A: Allocator> fmt::Debug for Weak<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_after_drop() {
+        let rc = Rc::new(String::from("hello"));
+        let weak = Rc::downgrade(&rc);
+        assert_eq!(Rc::strong_count(&rc), 1);
+        assert_eq!(Rc::weak_count(&rc), 1);
+        assert_eq!(&*weak.upgrade().unwrap(), "hello");
+
+        drop(rc);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+        assert_eq!(weak.weak_count(), 1);
+    }
+
+    #[test]
+    fn value_dropped_before_allocation() {
+        struct Flag<'a>(&'a Cell<bool>);
+        impl Drop for Flag<'_> {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let dropped = Cell::new(false);
+        let rc = Rc::new(Flag(&dropped));
+        let weak = Rc::downgrade(&rc);
+        let weak2 = weak.clone();
+        drop(rc);
+        assert!(dropped.get());
+        drop(weak);
+        assert!(weak2.upgrade().is_none());
+    }
+
+    #[test]
+    fn unsized_values() {
+        let s: Rc<str> = Rc::from("hello");
+        assert_eq!(&*s, "hello");
+
+        let slice: Rc<[String]> = vec![String::from("a"), String::from("b")].into();
+        let weak = Rc::downgrade(&slice);
+        assert_eq!(slice.len(), 2);
+        assert_eq!(slice[1], "b");
+        drop(slice);
+        assert!(weak.upgrade().is_none());
+
+        let collected: Rc<[u64]> = (0..5).collect();
+        assert_eq!(&*collected, &[0, 1, 2, 3, 4]);
+
+        let empty: Rc<[()]> = Vec::new().into();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn trait_object() {
+        let b: Box<dyn Fn(u8) -> u8> = Box::new(|x| x * 2);
+        let f: Rc<dyn Fn(u8) -> u8> = Rc::from(b);
+        let g = Rc::clone(&f);
+        assert_eq!(f(2), 4);
+        assert_eq!(Rc::strong_count(&g), 2);
+
+        let d: Rc<dyn std::fmt::Debug> = Rc::from(Box::new(String::from("x")) as Box<_>);
+        assert_eq!(format!("{:?}", &*d), "\"x\"");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn coerce_to_trait_object() {
+        let rc: Rc<dyn std::fmt::Display> = Rc::new(5);
+        let weak: Weak<dyn std::fmt::Display> = Rc::downgrade(&rc);
+        assert_eq!(weak.upgrade().unwrap().to_string(), "5");
+    }
+
+    #[test]
+    fn get_mut_and_make_mut() {
+        let mut a = Rc::new(String::from("a"));
+        Rc::get_mut(&mut a).unwrap().push('b');
+
+        let b = Rc::clone(&a);
+        assert!(Rc::get_mut(&mut a).is_none());
+        Rc::make_mut(&mut a).push('c');
+        assert_eq!(*a, "abc");
+        assert_eq!(*b, "ab");
+        assert!(!Rc::ptr_eq(&a, &b));
+
+        let weak = Rc::downgrade(&a);
+        assert!(Rc::get_mut(&mut a).is_none());
+        Rc::make_mut(&mut a).push('d');
+        assert_eq!(*a, "abcd");
+        assert!(weak.upgrade().is_none());
+        assert!(Rc::get_mut(&mut a).is_some());
+    }
+
+    #[test]
+    fn try_unwrap_and_into_inner() {
+        let a = Rc::new(String::from("a"));
+        let b = Rc::clone(&a);
+        let weak = Rc::downgrade(&a);
+        let a = Rc::try_unwrap(a).unwrap_err();
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(Rc::into_inner(b), None);
+        assert_eq!(Rc::into_inner(a).as_deref(), Some("a"));
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn raw_roundtrip() {
+        let a = Rc::new(7u8);
+        let ptr = Rc::into_raw(Rc::clone(&a));
+        assert_eq!(unsafe { *ptr }, 7);
+        let b = unsafe { Rc::from_raw(ptr) };
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(Rc::strong_count(&a), 2);
+
+        let s: Rc<str> = Rc::from("unsized");
+        let s = unsafe { Rc::from_raw(Rc::into_raw(s)) };
+        assert_eq!(&*s, "unsized");
+    }
+
+    #[test]
+    fn empty_weak() {
+        let weak = Weak::<i32>::new();
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+        assert_eq!(weak.weak_count(), 0);
+    }
+
+    #[test]
+    fn counting_allocator_sees_every_drop_path() {
+        use crate::alloc::CountingAllocator;
+
+        let counter = CountingAllocator::new();
+        {
+            // last Rc goes before the last Weak, and the other way around.
+            let a = Rc::new_in(String::from("a"), &counter);
+            let weak = Rc::downgrade(&a);
+            let b = Rc::clone(&a);
+            drop(a);
+            drop(b);
+            assert_eq!(counter.live_allocations(), 1);
+            drop(weak);
+            counter.assert_no_leaks();
+
+            let a = Rc::new_in(1u64, &counter);
+            drop(Rc::downgrade(&a));
+            drop(a);
+            counter.assert_no_leaks();
+
+            let a = Rc::new_in(String::from("b"), &counter);
+            let weak = Rc::downgrade(&a);
+            assert_eq!(Rc::try_unwrap(a).ok().as_deref(), Some("b"));
+            drop(weak);
+            counter.assert_no_leaks();
+
+            // make_mut moves the value out from under a Weak, and clones it away from an Rc.
+            let mut a = Rc::new_in(vec![1], &counter);
+            let weak = Rc::downgrade(&a);
+            Rc::make_mut(&mut a).push(2);
+            let b = Rc::clone(&a);
+            Rc::make_mut(&mut a).push(3);
+            assert_eq!(counter.live_allocations(), 3);
+            assert_eq!((&*a, &*b), (&vec![1, 2, 3], &vec![1, 2]));
+            drop((a, b, weak));
+            assert!(Weak::<u8, _>::new_in(&counter).upgrade().is_none());
         }
+        counter.assert_no_leaks();
+        assert_eq!(counter.total_allocations(), 6);
+    }
+
+    #[test]
+    fn std_traits() {
+        use std::collections::{BTreeSet, HashMap};
+
+        let mut map = HashMap::new();
+        map.insert(Rc::<str>::from("key"), 1);
+        // Borrow lets us look up with a plain &str.
+        assert_eq!(map.get("key"), Some(&1));
+
+        let set: BTreeSet<_> = [3, 1, 2].iter().map(|&x| Rc::from(x)).collect();
+        assert_eq!(set.iter().map(|x| **x).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let a = Rc::new(String::from("a"));
+        assert_eq!(a, Rc::new(String::from("a")));
+        assert_eq!(format!("{} {:?}", a, a), "a \"a\"");
+        assert_eq!(format!("{:p}", a), format!("{:p}", Rc::as_ptr(&a)));
+        assert_eq!(format!("{:?}", Rc::downgrade(&a)), "(Weak)");
+        assert_eq!(*Rc::<u8>::default(), 0);
+        let s: &String = a.as_ref();
+        assert_eq!(s, "a");
+    }
+
+    #[test]
+    fn pinned_future() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = Rc::pin(async { 5 });
+        let other = future.clone();
+        assert!(Rc::pin_get_mut(&mut future).is_none());
+        drop(other);
+        let polled = Rc::pin_get_mut(&mut future).unwrap().poll(&mut cx);
+        assert_eq!(polled, Poll::Ready(5));
+    }
+
+    #[test]
+    fn projection() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        struct Task<F> {
+            name: String,
+            future: F,
+        }
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let task = Rc::pin(Task {
+            name: String::from("task"),
+            future: async { 5 },
+        });
+        let mut name = unsafe { Rc::project(task.clone(), |task| &task.name) };
+        let mut future = unsafe { Rc::project(task, |task| &task.future) };
+        assert_eq!(format!("{:?}", name), "\"task\"");
+        // the name's Rc counts, so we can't poll the future.
+        assert!(Projection::get_mut(&mut future).is_none());
+        assert!(Projection::get_mut(&mut name).is_none());
+        drop(name);
+
+        let polled = Projection::get_mut(&mut future).unwrap().poll(&mut cx);
+        assert_eq!(polled, Poll::Ready(5));
+        let task = Projection::into_rc(future);
+        assert_eq!(task.name, "task");
     }
 }
@@ -1,16 +1,180 @@
 use crate::cell::Cell;
 use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::ptr::NonNull;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 enum RefState {
     Unshared,
     Shared(usize),
-    Exclusive,
+    // more than one if a RefMut has been split into RefMuts to disjoint parts.
+    Exclusive(usize),
+}
+
+impl fmt::Display for RefState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefState::Unshared => write!(f, "not borrowed"),
+            RefState::Shared(1) => write!(f, "immutably borrowed once"),
+            RefState::Shared(n) => write!(f, "immutably borrowed {} times", n),
+            RefState::Exclusive(1) => write!(f, "mutably borrowed"),
+            RefState::Exclusive(n) => write!(f, "mutably borrowed in {} parts", n),
+        }
+    }
+}
+
+/// The error returned by [`RefCell::try_borrow`], when the value is mutably borrowed.
+#[derive(Debug)]
+pub struct BorrowError {
+    state: RefState,
+    sites: LiveSites,
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot borrow immutably: value is {}{}",
+            self.state, self.sites
+        )
+    }
+}
+
+impl std::error::Error for BorrowError {}
+
+/// The error returned by [`RefCell::try_borrow_mut`], when the value is borrowed at all.
+#[derive(Debug)]
+pub struct BorrowMutError {
+    state: RefState,
+    sites: LiveSites,
+}
+
+impl fmt::Display for BorrowMutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot borrow mutably: value is {}{}",
+            self.state, self.sites
+        )
+    }
+}
+
+impl std::error::Error for BorrowMutError {}
+
+// with the `track-borrows` feature, every RefCell records where its live borrows were created,
+// so a borrow failure can point at the conflicting borrows.
+// without it, all of this compiles down to nothing.
+
+#[cfg(feature = "track-borrows")]
+struct BorrowSites {
+    // one slot per live borrow. slots of released borrows are None and get reused.
+    sites: UnsafeCell<Vec<Option<&'static Location<'static>>>>,
+}
+
+#[cfg(feature = "track-borrows")]
+impl BorrowSites {
+    fn new() -> Self {
+        BorrowSites {
+            sites: UnsafeCell::new(Vec::new()),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Vec<Option<&'static Location<'static>>>) -> R) -> R {
+        // SAFETY: RefCell is !Sync, and we never give out references into the Vec,
+        // so this is the only reference to it.
+        f(unsafe { &mut *self.sites.get() })
+    }
+
+    fn insert(&self, site: &'static Location<'static>) -> SiteId {
+        self.with(|sites| match sites.iter().position(Option::is_none) {
+            Some(i) => {
+                sites[i] = Some(site);
+                SiteId(i)
+            }
+            None => {
+                sites.push(Some(site));
+                SiteId(sites.len() - 1)
+            }
+        })
+    }
+
+    fn remove(&self, id: SiteId) {
+        self.with(|sites| {
+            sites[id.0] = None;
+            while let Some(None) = sites.last() {
+                sites.pop();
+            }
+        })
+    }
+
+    fn live(&self) -> LiveSites {
+        LiveSites(self.with(|sites| sites.iter().flatten().copied().collect()))
+    }
+}
+
+#[cfg(feature = "track-borrows")]
+#[derive(Copy, Clone)]
+struct SiteId(usize);
+
+#[cfg(feature = "track-borrows")]
+#[derive(Debug)]
+struct LiveSites(Vec<&'static Location<'static>>);
+
+#[cfg(feature = "track-borrows")]
+impl fmt::Display for LiveSites {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, site) in self.0.iter().enumerate() {
+            let sep = if i == 0 { " (borrowed at " } else { ", " };
+            write!(f, "{}{}", sep, site)?;
+        }
+        if !self.0.is_empty() {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "track-borrows"))]
+struct BorrowSites;
+
+#[cfg(not(feature = "track-borrows"))]
+impl BorrowSites {
+    fn new() -> Self {
+        BorrowSites
+    }
+
+    fn insert(&self, _site: &'static Location<'static>) -> SiteId {
+        SiteId
+    }
+
+    fn remove(&self, _id: SiteId) {}
+
+    fn live(&self) -> LiveSites {
+        LiveSites
+    }
+}
+
+#[cfg(not(feature = "track-borrows"))]
+#[derive(Copy, Clone)]
+struct SiteId;
+
+#[cfg(not(feature = "track-borrows"))]
+#[derive(Debug)]
+struct LiveSites;
+
+#[cfg(not(feature = "track-borrows"))]
+impl fmt::Display for LiveSites {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
 }
 
 pub struct RefCell<T> {
     value: UnsafeCell<T>,
     state: Cell<RefState>,
+    sites: BorrowSites,
 }
 
 // implied by UnsafeCell
@@ -21,94 +185,534 @@ impl<T> RefCell<T> {
         Self {
             value: UnsafeCell::new(value),
             state: Cell::new(RefState::Unshared),
+            sites: BorrowSites::new(),
+        }
+    }
+
+    /// Immutably borrows the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        match self.try_borrow() {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
         }
     }
 
-    pub fn borrow(&self) -> Option<Ref<'_, T>> {
+    /// Mutably borrows the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently borrowed.
+    #[track_caller]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        match self.try_borrow_mut() {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[track_caller]
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, BorrowError> {
         match self.state.get() {
             RefState::Unshared => {
                 self.state.set(RefState::Shared(1));
-                Some(Ref { refcell: self })
             }
             RefState::Shared(n) => {
                 self.state.set(RefState::Shared(n + 1));
-                Some(Ref { refcell: self })
             }
-            RefState::Exclusive => None,
+            state @ RefState::Exclusive(_) => {
+                return Err(BorrowError {
+                    state,
+                    sites: self.sites.live(),
+                })
+            }
+        }
+        Ok(Ref {
+            // SAFETY: UnsafeCell::get never returns a null pointer.
+            value: unsafe { NonNull::new_unchecked(self.value.get()) },
+            borrow: BorrowRef {
+                state: &self.state,
+                sites: &self.sites,
+                site: self.sites.insert(Location::caller()),
+            },
+        })
+    }
+
+    #[track_caller]
+    pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, BorrowMutError> {
+        match self.state.get() {
+            RefState::Unshared => {
+                self.state.set(RefState::Exclusive(1));
+                // SAFETY: no other references have been given out since state would be
+                // Shared or Exclusive.
+                Ok(RefMut {
+                    // SAFETY: UnsafeCell::get never returns a null pointer.
+                    value: unsafe { NonNull::new_unchecked(self.value.get()) },
+                    borrow: BorrowRefMut {
+                        state: &self.state,
+                        sites: &self.sites,
+                        site: self.sites.insert(Location::caller()),
+                    },
+                    _marker: PhantomData,
+                })
+            }
+            state => Err(BorrowMutError {
+                state,
+                sites: self.sites.live(),
+            }),
+        }
+    }
+}
+
+// the bookkeeping part of a Ref. it is independent of the borrowed type,
+// so it can be carried over when a Ref is mapped to a part of the value.
+struct BorrowRef<'refcell> {
+    state: &'refcell Cell<RefState>,
+    sites: &'refcell BorrowSites,
+    site: SiteId,
+}
+
+impl BorrowRef<'_> {
+    #[track_caller]
+    fn clone(&self) -> Self {
+        match self.state.get() {
+            RefState::Exclusive(_) | RefState::Unshared => unreachable!(),
+            RefState::Shared(n) => {
+                self.state.set(RefState::Shared(n + 1));
+            }
+        }
+        BorrowRef {
+            state: self.state,
+            sites: self.sites,
+            site: self.sites.insert(Location::caller()),
         }
     }
+}
 
-    pub fn borrow_mut(&self) -> Option<RefMut<'_, T>> {
-        if let RefState::Unshared = self.state.get() {
-            self.state.set(RefState::Exclusive);
-            // SAFETY: no other references have been given out since state would be
-            // Shared or Exclusive.
-            Some(RefMut { refcell: self })
-        } else {
-            None
+impl Drop for BorrowRef<'_> {
+    fn drop(&mut self) {
+        self.sites.remove(self.site);
+        match self.state.get() {
+            RefState::Exclusive(_) | RefState::Unshared => unreachable!(),
+            RefState::Shared(1) => {
+                self.state.set(RefState::Unshared);
+            }
+            RefState::Shared(n) => {
+                self.state.set(RefState::Shared(n - 1));
+            }
         }
     }
 }
 
-pub struct Ref<'refcell, T> {
-    refcell: &'refcell RefCell<T>,
+pub struct Ref<'refcell, T: ?Sized> {
+    // points into the RefCell, at a part of its value if the Ref has been mapped.
+    value: NonNull<T>,
+    borrow: BorrowRef<'refcell>,
 }
 
-impl<T> std::ops::Deref for Ref<'_, T> {
+impl<'refcell, T: ?Sized> Ref<'refcell, T> {
+    /// Creates another `Ref` to the same value.
+    ///
+    /// This is an associated function, so that it doesn't shadow `clone` on the value.
+    #[track_caller]
+    #[allow(clippy::should_implement_trait)]
+    pub fn clone(orig: &Self) -> Self {
+        Ref {
+            value: orig.value,
+            borrow: orig.borrow.clone(),
+        }
+    }
+
+    /// Turns the `Ref` into a `Ref` to a part of the value.
+    pub fn map<U: ?Sized, F>(orig: Self, f: F) -> Ref<'refcell, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        Ref {
+            value: NonNull::from(f(&*orig)),
+            borrow: orig.borrow,
+        }
+    }
+
+    /// Turns the `Ref` into a `Ref` to a part of the value, if there is one.
+    /// Otherwise, the original `Ref` is given back.
+    pub fn filter_map<U: ?Sized, F>(orig: Self, f: F) -> Result<Ref<'refcell, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        match f(&*orig).map(NonNull::from) {
+            Some(value) => Ok(Ref {
+                value,
+                borrow: orig.borrow,
+            }),
+            None => Err(orig),
+        }
+    }
+
+    /// Splits the `Ref` into two `Ref`s to parts of the value.
+    #[track_caller]
+    pub fn map_split<U: ?Sized, V: ?Sized, F>(
+        orig: Self,
+        f: F,
+    ) -> (Ref<'refcell, U>, Ref<'refcell, V>)
+    where
+        F: FnOnce(&T) -> (&U, &V),
+    {
+        let (a, b) = f(&*orig);
+        let (a, b) = (NonNull::from(a), NonNull::from(b));
+        let borrow = orig.borrow.clone();
+        (
+            Ref {
+                value: a,
+                borrow: orig.borrow,
+            },
+            Ref { value: b, borrow },
+        )
+    }
+}
+
+impl<T: ?Sized> std::ops::Deref for Ref<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
         // SAFETY
         // a Ref is only created if no exclusive references have been given out.
         // once it is given out, state is set to Shared, so no exclusive references are given out.
         // so dereferencing into a shared reference is fine.
-        unsafe { &*self.refcell.value.get() }
+        unsafe { self.value.as_ref() }
     }
 }
 
-impl<T> Drop for Ref<'_, T> {
+// the bookkeeping part of a RefMut, see BorrowRef.
+struct BorrowRefMut<'refcell> {
+    state: &'refcell Cell<RefState>,
+    sites: &'refcell BorrowSites,
+    site: SiteId,
+}
+
+impl BorrowRefMut<'_> {
+    // only valid if the two resulting RefMuts point to disjoint parts of the value.
+    #[track_caller]
+    fn split(&self) -> Self {
+        match self.state.get() {
+            RefState::Shared(_) | RefState::Unshared => unreachable!(),
+            RefState::Exclusive(n) => {
+                self.state.set(RefState::Exclusive(n + 1));
+            }
+        }
+        BorrowRefMut {
+            state: self.state,
+            sites: self.sites,
+            site: self.sites.insert(Location::caller()),
+        }
+    }
+}
+
+impl Drop for BorrowRefMut<'_> {
     fn drop(&mut self) {
-        match self.refcell.state.get() {
-            RefState::Exclusive | RefState::Unshared => unreachable!(),
-            RefState::Shared(1) => {
-                self.refcell.state.set(RefState::Unshared);
+        self.sites.remove(self.site);
+        match self.state.get() {
+            RefState::Shared(_) | RefState::Unshared => unreachable!(),
+            RefState::Exclusive(1) => {
+                self.state.set(RefState::Unshared);
             }
-            RefState::Shared(n) => {
-                self.refcell.state.set(RefState::Shared(n - 1));
+            RefState::Exclusive(n) => {
+                self.state.set(RefState::Exclusive(n - 1));
             }
         }
     }
 }
 
-pub struct RefMut<'refcell, T> {
-    refcell: &'refcell RefCell<T>,
+pub struct RefMut<'refcell, T: ?Sized> {
+    // points into the RefCell, at a part of its value if the RefMut has been mapped.
+    value: NonNull<T>,
+    borrow: BorrowRefMut<'refcell>,
+    // we hand out &mut T, so we have to be invariant over T, just like &mut T.
+    _marker: PhantomData<&'refcell mut T>,
+}
+
+impl<'refcell, T: ?Sized> RefMut<'refcell, T> {
+    /// Turns the `RefMut` into a `RefMut` to a part of the value.
+    pub fn map<U: ?Sized, F>(mut orig: Self, f: F) -> RefMut<'refcell, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        RefMut {
+            value: NonNull::from(f(&mut *orig)),
+            borrow: orig.borrow,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Turns the `RefMut` into a `RefMut` to a part of the value, if there is one.
+    /// Otherwise, the original `RefMut` is given back.
+    pub fn filter_map<U: ?Sized, F>(mut orig: Self, f: F) -> Result<RefMut<'refcell, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        match f(&mut *orig).map(NonNull::from) {
+            Some(value) => Ok(RefMut {
+                value,
+                borrow: orig.borrow,
+                _marker: PhantomData,
+            }),
+            None => Err(orig),
+        }
+    }
+
+    /// Splits the `RefMut` into two `RefMut`s to disjoint parts of the value.
+    ///
+    /// The value stays exclusively borrowed until both of them are dropped.
+    #[track_caller]
+    pub fn map_split<U: ?Sized, V: ?Sized, F>(
+        mut orig: Self,
+        f: F,
+    ) -> (RefMut<'refcell, U>, RefMut<'refcell, V>)
+    where
+        F: FnOnce(&mut T) -> (&mut U, &mut V),
+    {
+        // the two &mut are disjoint, since they were both alive at the same time.
+        let (a, b) = f(&mut *orig);
+        let (a, b) = (NonNull::from(a), NonNull::from(b));
+        let borrow = orig.borrow.split();
+        (
+            RefMut {
+                value: a,
+                borrow: orig.borrow,
+                _marker: PhantomData,
+            },
+            RefMut {
+                value: b,
+                borrow,
+                _marker: PhantomData,
+            },
+        )
+    }
 }
 
-impl<T> std::ops::Deref for RefMut<'_, T> {
+impl<T: ?Sized> std::ops::Deref for RefMut<'_, T> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
         // SAFETY
         // see safety for DerefMut
-        unsafe { &*self.refcell.value.get() }
+        unsafe { self.value.as_ref() }
     }
 }
 
-impl<T> std::ops::DerefMut for RefMut<'_, T> {
+impl<T: ?Sized> std::ops::DerefMut for RefMut<'_, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         // SAFETY
         // a RefMut is only created if no other references have been given out.
         // once it is given out, state is set to Exclusive, so no future references are given out.
         // so we have an exclusive lease on the inner value, so mutably dereferencing is fine.
-        unsafe { &mut *self.refcell.value.get() }
+        // a split RefMut only points to its own disjoint part of the value.
+        unsafe { self.value.as_mut() }
     }
 }
 
-impl<T> Drop for RefMut<'_, T> {
-    fn drop(&mut self) {
-        match self.refcell.state.get() {
-            RefState::Shared(_) | RefState::Unshared => unreachable!(),
-            RefState::Exclusive => {
-                self.refcell.state.set(RefState::Unshared);
+impl<T: Clone> Clone for RefCell<T> {
+    /// # Panics
+    ///
+    /// Panics if the value is currently mutably borrowed.
+    #[track_caller]
+    fn clone(&self) -> Self {
+        RefCell::new(self.borrow().clone())
+    }
+}
+
+impl<T: Default> Default for RefCell<T> {
+    fn default() -> Self {
+        RefCell::new(T::default())
+    }
+}
+
+impl<T> From<T> for RefCell<T> {
+    fn from(value: T) -> Self {
+        RefCell::new(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // printing must not panic, so a mutably borrowed value is left out.
+        struct Borrowed;
+        impl fmt::Debug for Borrowed {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("<borrowed>")
             }
         }
+
+        let mut d = f.debug_struct("RefCell");
+        match self.try_borrow() {
+            Ok(value) => d.field("value", &&*value),
+            Err(_) => d.field("value", &Borrowed),
+        };
+        d.finish()
+    }
+}
+
+// the comparisons panic if either value is currently mutably borrowed, like std's.
+impl<T: PartialEq> PartialEq for RefCell<T> {
+    #[track_caller]
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+impl<T: Eq> Eq for RefCell<T> {}
+
+impl<T: PartialOrd> PartialOrd for RefCell<T> {
+    #[track_caller]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.borrow().partial_cmp(&*other.borrow())
+    }
+}
+
+impl<T: Ord> Ord for RefCell<T> {
+    #[track_caller]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.borrow().cmp(&*other.borrow())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for RefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_borrow_errors() {
+        let cell = RefCell::new(5);
+        let r1 = cell.borrow();
+        let r2 = cell.try_borrow().unwrap();
+        let err = cell.try_borrow_mut().err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("cannot borrow mutably: value is immutably borrowed 2 times"));
+        drop((r1, r2));
+
+        let mut w = cell.borrow_mut();
+        *w += 1;
+        let err = cell.try_borrow().err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("cannot borrow immutably: value is mutably borrowed"));
+        drop(w);
+        assert_eq!(*cell.borrow(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot borrow mutably: value is immutably borrowed once")]
+    fn borrow_mut_while_shared() {
+        let cell = RefCell::new(5);
+        let _r = cell.borrow();
+        let _w = cell.borrow_mut();
+    }
+
+    #[test]
+    fn map_and_clone() {
+        let cell = RefCell::new((1, vec![2, 3]));
+        let second = Ref::map(cell.borrow(), |(_, v)| &v[..]);
+        let second2 = Ref::clone(&second);
+        assert_eq!(*second2, [2, 3]);
+        let missing = Ref::filter_map(second, |v| v.get(5));
+        let second = missing.err().unwrap();
+        drop(second2);
+        assert!(cell.try_borrow_mut().is_err());
+        drop(second);
+        assert!(cell.try_borrow_mut().is_ok());
+
+        let mut first = RefMut::map(cell.borrow_mut(), |(a, _)| a);
+        *first += 10;
+        drop(first);
+        assert_eq!(cell.borrow().0, 11);
+    }
+
+    #[test]
+    fn map_split_keeps_exclusive_until_both_dropped() {
+        let cell = RefCell::new([1, 2, 3, 4]);
+        let (mut left, mut right) = RefMut::map_split(cell.borrow_mut(), |a| a.split_at_mut(2));
+        left[0] = 10;
+        right[1] = 40;
+        let err = cell.try_borrow().err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("cannot borrow immutably: value is mutably borrowed in 2 parts"));
+        drop(left);
+        assert!(cell.try_borrow().is_err());
+        drop(right);
+        assert_eq!(*cell.borrow(), [10, 2, 3, 40]);
+
+        let (a, b) = Ref::map_split(cell.borrow(), |a| (&a[0], &a[3]));
+        assert!(cell.try_borrow_mut().is_err());
+        assert_eq!((*a, *b), (10, 40));
+        drop((a, b));
+        assert!(cell.try_borrow_mut().is_ok());
+    }
+
+    #[cfg(feature = "track-borrows")]
+    #[test]
+    fn reports_borrow_sites() {
+        let cell = RefCell::new(5);
+        let r1 = cell.borrow();
+        let line1 = line!() - 1;
+        let r2 = cell.borrow();
+        let line2 = line!() - 1;
+        drop(r1);
+
+        let err = cell.try_borrow_mut().err().unwrap().to_string();
+        assert!(!err.contains(&format!("{}:{}:", file!(), line1)), "{}", err);
+        assert!(err.contains(&format!("{}:{}:", file!(), line2)), "{}", err);
+
+        drop(r2);
+        let _w = cell.borrow_mut();
+        let line3 = line!() - 1;
+        let err = cell.try_borrow().err().unwrap().to_string();
+        assert!(err.contains(&format!("{}:{}:", file!(), line3)), "{}", err);
+    }
+
+    #[test]
+    fn std_traits() {
+        let a = RefCell::new(vec![1]);
+        let b = a.clone();
+        b.borrow_mut().push(2);
+        assert!(a < b);
+        assert_eq!(a, RefCell::from(vec![1]));
+        assert_eq!(format!("{:?}", a), "RefCell { value: [1] }");
+
+        let r = a.borrow();
+        assert_eq!(format!("{:?} {:?}", a, r), "RefCell { value: [1] } [1]");
+        drop(r);
+        let w = a.borrow_mut();
+        assert_eq!(
+            format!("{:?} {:?}", a, w),
+            "RefCell { value: <borrowed> } [1]"
+        );
     }
 }
@@ -0,0 +1,405 @@
+//! `Serialize` and `Deserialize` for [`Cell`], [`RefCell`] and [`Rc`].
+//!
+//! By default, an `Rc` is written out like its value. A value that is shared by several `Rc`s
+//! is therefore written once per `Rc`, and comes back as separate allocations.
+//!
+//! Wrapping the outermost value in [`Sharing`] (or going through [`serialize_shared`] and
+//! [`deserialize_shared`]) writes each allocation only once, and refers back to it by id
+//! afterwards, so a DAG of `Rc`s round-trips with its sharing intact:
+//!
+//! ```
+//! use smart_pointers::rc::Rc;
+//! use smart_pointers::serde::Sharing;
+//!
+//! let leaf = Rc::new(String::from("leaf"));
+//! let tree = vec![Rc::clone(&leaf), leaf];
+//!
+//! let json = serde_json::to_string(&Sharing(&tree)).unwrap();
+//! let Sharing(back): Sharing<Vec<Rc<String>>> = serde_json::from_str(&json).unwrap();
+//! assert!(Rc::ptr_eq(&back[0], &back[1]));
+//! ```
+//!
+//! Reading with sharing needs a `'static` value, so that the `Rc`s in it can be told apart by
+//! their type. Without it, like with serde's own impl, any `Rc` can be read, including ones
+//! that borrow from the input.
+//!
+//! Cycles can't be represented this way: a reference back to an `Rc` whose value is still
+//! being read fails to deserialize.
+
+use crate::alloc::Allocator;
+use crate::cell::Cell;
+use crate::rc::Rc;
+use crate::refcell::RefCell;
+use ::serde::de::Error as _;
+use ::serde::ser::Error as _;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem;
+use std::thread::LocalKey;
+
+impl<T: Copy + Serialize> Serialize for Cell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Cell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Cell::new)
+    }
+}
+
+/// Fails if the value is currently mutably borrowed.
+impl<T: Serialize> Serialize for RefCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.try_borrow() {
+            Ok(value) => value.serialize(serializer),
+            Err(e) => Err(S::Error::custom(e)),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for RefCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(RefCell::new)
+    }
+}
+
+// how an Rc is written out while sharing is preserved: its value the first time we come across
+// the allocation, and only the id of the allocation every time after that.
+#[derive(Serialize)]
+#[serde(rename = "Rc")]
+enum SharedRcRef<'a, T: ?Sized> {
+    Def(usize, &'a T),
+    Ref(usize),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Rc")]
+enum SharedRc<T> {
+    Def(usize, T),
+    Ref(usize),
+}
+
+thread_local! {
+    // the ids of the allocations written so far, by address. Some while serialize_shared runs.
+    static WRITTEN: RefCell<Option<HashMap<*const (), usize>>> = RefCell::new(None);
+    // the Rcs read so far, by id. Some while deserialize_shared runs.
+    static READ: RefCell<Option<HashMap<usize, ReadRc>>> = RefCell::new(None);
+}
+
+// TypeId::of::<T>(), without the T: 'static bound. types that only differ in their lifetimes
+// get the same id.
+fn erased_type_id<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let phantom = PhantomData::<T>;
+    // SAFETY: only the lifetime of the trait object changes, which type_id doesn't look at:
+    // lifetimes are gone by the time its code is generated.
+    let phantom =
+        unsafe { mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&phantom) };
+    phantom.type_id()
+}
+
+// an Rc read by deserialize_shared, with its type erased. reading an Rc<T> doesn't need
+// T: 'static, so this stands in for a Box<dyn Any>.
+struct ReadRc {
+    // erased_type_id of the Rc.
+    ty: TypeId,
+    // a Box<Rc<T, A>>.
+    rc: *mut (),
+    drop: unsafe fn(*mut ()),
+}
+
+impl ReadRc {
+    fn new<T: ?Sized, A: Allocator>(rc: Rc<T, A>) -> Self {
+        unsafe fn drop<T: ?Sized, A: Allocator>(rc: *mut ()) {
+            mem::drop(Box::from_raw(rc as *mut Rc<T, A>));
+        }
+        ReadRc {
+            ty: erased_type_id::<Rc<T, A>>(),
+            rc: Box::into_raw(Box::new(rc)) as *mut (),
+            drop: drop::<T, A>,
+        }
+    }
+
+    fn get<T: ?Sized, A: Allocator + Clone>(&self) -> Option<Rc<T, A>> {
+        if self.ty != erased_type_id::<Rc<T, A>>() {
+            return None;
+        }
+        // SAFETY: it's an Rc<T, A>, up to lifetimes. deserialize_shared only reads 'static
+        // values, so the Rcs that make them up are 'static as well.
+        Some(unsafe { &*(self.rc as *const Rc<T, A>) }.clone())
+    }
+}
+
+impl Drop for ReadRc {
+    fn drop(&mut self) {
+        // SAFETY: made by ReadRc::new for this pointer.
+        unsafe { (self.drop)(self.rc) }
+    }
+}
+
+impl<T: ?Sized + Serialize, A: Allocator> Serialize for Rc<T, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let addr = Rc::as_ptr(self) as *const ();
+        let id = WRITTEN.with(|written| {
+            let mut written = written.borrow_mut();
+            let written = written.as_mut()?;
+            let next = written.len();
+            let id = *written.entry(addr).or_insert(next);
+            Some((id, id == next))
+        });
+        match id {
+            None => (**self).serialize(serializer),
+            Some((id, true)) => SharedRcRef::Def(id, &**self).serialize(serializer),
+            Some((id, false)) => SharedRcRef::<T>::Ref(id).serialize(serializer),
+        }
+    }
+}
+
+/// Places the value in a default `A`.
+impl<'de, T: ?Sized, A: Allocator + Clone + Default> Deserialize<'de> for Rc<T, A>
+where
+    Box<T>: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if READ.with(|read| read.borrow().is_none()) {
+            return Box::<T>::deserialize(deserializer).map(|b| Rc::from_box_in(b, A::default()));
+        }
+        match SharedRc::<Box<T>>::deserialize(deserializer)? {
+            SharedRc::Def(id, value) => {
+                let rc = Rc::from_box_in(value, A::default());
+                let read_rc = ReadRc::new(Rc::clone(&rc));
+                READ.with(|read| {
+                    if let Some(read) = read.borrow_mut().as_mut() {
+                        read.insert(id, read_rc);
+                    }
+                });
+                Ok(rc)
+            }
+            SharedRc::Ref(id) => READ.with(|read| {
+                let read = read.borrow();
+                let rc = read
+                    .as_ref()
+                    .and_then(|read| read.get(&id))
+                    .ok_or_else(|| {
+                        D::Error::custom(format_args!("reference to Rc {} before its value", id))
+                    })?;
+                rc.get().ok_or_else(|| {
+                    D::Error::custom(format_args!("Rc {} is read as two different types", id))
+                })
+            }),
+        }
+    }
+}
+
+// sets up the bookkeeping in `state` for the duration of a call, unless an outer call already did.
+struct Session<M: 'static> {
+    state: &'static LocalKey<RefCell<Option<M>>>,
+    outermost: bool,
+}
+
+impl<M: Default> Session<M> {
+    fn start(state: &'static LocalKey<RefCell<Option<M>>>) -> Self {
+        let outermost = state.with(|state| {
+            let mut state = state.borrow_mut();
+            let outermost = state.is_none();
+            if outermost {
+                *state = Some(M::default());
+            }
+            outermost
+        });
+        Session { state, outermost }
+    }
+}
+
+impl<M> Drop for Session<M> {
+    fn drop(&mut self) {
+        if self.outermost {
+            // dropped outside of the borrow, since the map may own the last Rcs of some values.
+            let state = self.state.with(|state| state.borrow_mut().take());
+            drop(state);
+        }
+    }
+}
+
+/// Serializes `value`, writing every `Rc` allocation in it only once.
+///
+/// Can be used with `#[serde(serialize_with = "...")]`.
+pub fn serialize_shared<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + Serialize,
+    S: Serializer,
+{
+    let _session = Session::start(&WRITTEN);
+    value.serialize(serializer)
+}
+
+/// Deserializes a value written by [`serialize_shared`], restoring the sharing between its `Rc`s.
+///
+/// The value has to be `'static`, so that the `Rc`s in it can be told apart by their type.
+///
+/// Can be used with `#[serde(deserialize_with = "...")]`.
+pub fn deserialize_shared<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + 'static,
+    D: Deserializer<'de>,
+{
+    let _session = Session::start(&READ);
+    T::deserialize(deserializer)
+}
+
+/// Serializes and deserializes the wrapped value with [`serialize_shared`] and
+/// [`deserialize_shared`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sharing<T>(pub T);
+
+impl<T: Serialize> Serialize for Sharing<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_shared(&self.0, serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de> + 'static> Deserialize<'de> for Sharing<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_shared(deserializer).map(Sharing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::{AllocError, Global};
+    use std::alloc::Layout;
+    use std::ptr::NonNull;
+
+    #[derive(Serialize, Deserialize)]
+    struct Node {
+        name: String,
+        visits: Cell<u32>,
+        children: RefCell<Vec<Rc<Node>>>,
+    }
+
+    fn node(name: &str, children: Vec<Rc<Node>>) -> Rc<Node> {
+        Rc::new(Node {
+            name: name.to_string(),
+            visits: Cell::new(0),
+            children: RefCell::new(children),
+        })
+    }
+
+    // a -> b -> d
+    //   -> c -> d
+    fn diamond() -> Rc<Node> {
+        let d = node("d", vec![]);
+        d.visits.set(3);
+        let b = node("b", vec![Rc::clone(&d)]);
+        let c = node("c", vec![d]);
+        node("a", vec![b, c])
+    }
+
+    fn bottoms(a: &Node) -> (Rc<Node>, Rc<Node>) {
+        let children = a.children.borrow();
+        let b = Rc::clone(&children[0].children.borrow()[0]);
+        let c = Rc::clone(&children[1].children.borrow()[0]);
+        (b, c)
+    }
+
+    #[test]
+    fn plain_round_trip_duplicates_shared_values() {
+        let json = serde_json::to_string(&diamond()).unwrap();
+        let a: Rc<Node> = serde_json::from_str(&json).unwrap();
+        let (d1, d2) = bottoms(&a);
+        assert_eq!((d1.name.as_str(), d1.visits.get()), ("d", 3));
+        assert!(!Rc::ptr_eq(&d1, &d2));
+    }
+
+    #[test]
+    fn sharing_round_trip_keeps_dag() {
+        let a = diamond();
+        let json = serde_json::to_string(&Sharing(&a)).unwrap();
+        // d is only written out once.
+        assert_eq!(json.matches("\"d\"").count(), 1);
+
+        let Sharing(back): Sharing<Rc<Node>> = serde_json::from_str(&json).unwrap();
+        let (d1, d2) = bottoms(&back);
+        assert!(Rc::ptr_eq(&d1, &d2));
+        // the bookkeeping has been released, so these are the only references.
+        assert_eq!(Rc::strong_count(&d1), 4);
+        drop(back);
+        assert_eq!(Rc::strong_count(&d1), 2);
+    }
+
+    #[test]
+    fn plain_rcs_may_borrow() {
+        let json = r#"["borrowed","borrowed"]"#;
+        let v: Vec<Rc<&str>> = serde_json::from_str(json).unwrap();
+        assert_eq!(*v[0], "borrowed");
+        assert!(!Rc::ptr_eq(&v[0], &v[1]));
+    }
+
+    thread_local! {
+        static LIVE: Cell<usize> = Cell::new(0);
+    }
+
+    // the global allocator, counting the live allocations of this thread.
+    #[derive(Clone, Default)]
+    struct Counted;
+
+    unsafe impl Allocator for Counted {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, AllocError> {
+            LIVE.with(|live| live.set(live.get() + 1));
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            LIVE.with(|live| live.set(live.get() - 1));
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn other_allocators() {
+        let leaf: Rc<str> = Rc::from("leaf");
+        let json = serde_json::to_string(&Sharing(vec![Rc::clone(&leaf), leaf])).unwrap();
+
+        let plain: Rc<[u8], Counted> = serde_json::from_str("[1,2]").unwrap();
+        assert_eq!((&*plain, LIVE.with(Cell::get)), (&[1, 2][..], 1));
+        drop(plain);
+        let Sharing(back): Sharing<Vec<Rc<str, Counted>>> = serde_json::from_str(&json).unwrap();
+        assert!(Rc::ptr_eq(&back[0], &back[1]));
+        assert_eq!((&*back[0], LIVE.with(Cell::get)), ("leaf", 1));
+        drop(back);
+        assert_eq!(LIVE.with(Cell::get), 0);
+    }
+
+    #[test]
+    fn errors() {
+        let cell = RefCell::new(1);
+        let _w = cell.borrow_mut();
+        assert!(serde_json::to_string(&cell).is_err());
+
+        let dangling = r#"[{"Ref":0}]"#;
+        assert!(serde_json::from_str::<Sharing<Vec<Rc<u8>>>>(dangling).is_err());
+        let mistyped = r#"[{"Def":[0,1]},{"Ref":0}]"#;
+        assert!(serde_json::from_str::<Sharing<(Rc<u8>, Rc<u16>)>>(mistyped).is_err());
+        let misplaced = r#"[{"Def":[0,1]},{"Ref":0}]"#;
+        assert!(serde_json::from_str::<Sharing<(Rc<u8>, Rc<u8, Counted>)>>(misplaced).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+mod atomic_cell;
+mod condvar;
+mod mutex;
+mod parking;
+mod poison;
+mod rwlock;
+
+pub use atomic_cell::{AtomicCell, NoUninit};
+pub use condvar::Condvar;
+pub use mutex::{Mutex, MutexGuard};
+pub use poison::{LockResult, PoisonError, TryLockError, TryLockResult};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use std::alloc::{dealloc, Layout};
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+// a refcount this large means someone is leaking Arcs (mem::forget in a loop).
+// we abort before the count can overflow and free the value while it's still in use.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+struct ArcInner<T> {
+    // number of Arcs pointing at this allocation.
+    strong: AtomicUsize,
+    // number of Weaks pointing at this allocation, plus one held collectively by all Arcs.
+    // usize::MAX while get_mut is checking for uniqueness.
+    weak: AtomicUsize,
+    value: T,
+}
+
+/// The thread-safe counterpart of [`Rc`](crate::rc::Rc), using atomic reference counts.
+pub struct Arc<T> {
+    inner: NonNull<ArcInner<T>>,
+    _marker: PhantomData<ArcInner<T>>,
+}
+
+// an Arc hands out &T to multiple threads (needs Sync) and can drop T on any thread (needs Send).
+unsafe impl<T: Send + Sync> Send for Arc<T> {}
+unsafe impl<T: Send + Sync> Sync for Arc<T> {}
+
+impl<T> Arc<T> {
+    pub fn new(v: T) -> Self {
+        let inner = Box::new(ArcInner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            value: v,
+        });
+
+        Arc {
+            // SAFETY: Box does not give us a null pointer.
+            inner: unsafe { NonNull::new_unchecked(Box::into_raw(inner)) },
+            _marker: PhantomData,
+        }
+    }
+
+    fn inner(&self) -> &ArcInner<T> {
+        // SAFETY: the allocation is only freed once all Arcs and Weaks are gone.
+        // we have an Arc, therefore it has not been freed.
+        unsafe { self.inner.as_ref() }
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let mut cur = this.inner().weak.load(Ordering::Relaxed);
+        loop {
+            // get_mut has locked the weak count, wait for it to finish.
+            if cur == usize::MAX {
+                std::hint::spin_loop();
+                cur = this.inner().weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if cur > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire synchronizes with the Release store in is_unique,
+            // so no new Weak can be observed by an ongoing get_mut.
+            match this.inner().weak.compare_exchange_weak(
+                cur,
+                cur + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Weak {
+                        inner: this.inner,
+                        _marker: PhantomData,
+                    }
+                }
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Acquire)
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        let cnt = this.inner().weak.load(Ordering::Acquire);
+        if cnt == usize::MAX {
+            // the weak count is locked, so it was 1 (only the Arcs' shared weak reference).
+            0
+        } else {
+            // don't count the weak reference held by the Arcs.
+            cnt - 1
+        }
+    }
+
+    // whether we are the only Arc and there are no Weaks.
+    fn is_unique(&mut self) -> bool {
+        // lock the weak count if we are the only weak reference holder,
+        // so no Weak can be upgraded into a new Arc while we check the strong count.
+        // Acquire synchronizes with the Release decrement in Weak::drop.
+        if self
+            .inner()
+            .weak
+            .compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // Acquire synchronizes with the Release decrement in Arc::drop,
+            // so all accesses through other (now dropped) Arcs happen before ours.
+            let unique = self.inner().strong.load(Ordering::Acquire) == 1;
+            // Release synchronizes with the Acquire in downgrade.
+            self.inner().weak.store(1, Ordering::Release);
+            unique
+        } else {
+            false
+        }
+    }
+
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if this.is_unique() {
+            // SAFETY: we are the only Arc and there are no Weaks,
+            // and we hold &mut self, so nobody else can access the value.
+            Some(unsafe { &mut (*this.inner.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    pub fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        // temporarily setting the strong count to zero prevents Weaks from upgrading.
+        // Acquire synchronizes with the Release decrement of other Arcs being dropped.
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // there are other Arcs, so we clone the value into a fresh allocation.
+            *this = Arc::new((**this).clone());
+        } else if this.inner().weak.load(Ordering::Relaxed) != 1 {
+            // we were the only Arc, but there are Weaks. since the strong count is now zero,
+            // they can never upgrade again, so we move the value into a fresh allocation
+            // and leave them behind.
+            // this releases the weak reference held by the Arcs once we're done.
+            let _weak = Weak {
+                inner: this.inner,
+                _marker: PhantomData,
+            };
+            // SAFETY: the strong count is zero, so nobody else is going to read or drop the value.
+            // we overwrite `this` without dropping it, since its strong count is already released.
+            unsafe {
+                let value = ptr::read(&this.inner().value);
+                ptr::write(this, Arc::new(value));
+            }
+        } else {
+            // we were the only reference of any kind, restore the strong count.
+            this.inner().strong.store(1, Ordering::Release);
+        }
+
+        // SAFETY: at this point we hold the only Arc and there are no Weaks that can upgrade.
+        unsafe { &mut (*this.inner.as_ptr()).value }
+    }
+
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        // synchronize with the Release decrements of other Arcs, like in Drop.
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: the strong count is zero, so we are the last one to touch the value.
+        let value = unsafe { ptr::read(&this.inner().value) };
+        // release the weak reference held collectively by the Arcs.
+        drop(Weak {
+            inner: this.inner,
+            _marker: PhantomData,
+        });
+        std::mem::forget(this);
+        Ok(value)
+    }
+}
+
+impl<T> std::ops::Deref for Arc<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the value is only dropped when the last Arc goes away.
+        // we have an Arc, therefore the value has not been dropped, so deref is fine.
+        &self.inner().value
+    }
+}
+
+impl<T> Clone for Arc<T> {
+    fn clone(&self) -> Self {
+        // Relaxed is fine: we already have an Arc, so the value can't be dropped concurrently,
+        // and new references can only be formed from existing ones.
+        let old = self.inner().strong.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        Arc {
+            inner: self.inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Arc<T> {
+    fn drop(&mut self) {
+        // Release makes all our accesses to the value happen before the final drop.
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Acquire synchronizes with the Release decrements of all other Arcs,
+        // so their accesses to the value happen before we drop it.
+        atomic::fence(Ordering::Acquire);
+
+        // SAFETY: we were the _only_ Arc left, and we are being dropped.
+        // therefore, after us, there will be no Arcs, and no references to T.
+        unsafe { ptr::drop_in_place(&mut (*self.inner.as_ptr()).value) };
+
+        // release the weak reference held collectively by the Arcs.
+        drop(Weak {
+            inner: self.inner,
+            _marker: PhantomData,
+        });
+    }
+}
+
+/// A non-owning handle to the allocation of an [`Arc`].
+pub struct Weak<T> {
+    // dangling (usize::MAX) for a Weak created through Weak::new.
+    inner: NonNull<ArcInner<T>>,
+    _marker: PhantomData<ArcInner<T>>,
+}
+
+unsafe impl<T: Send + Sync> Send for Weak<T> {}
+unsafe impl<T: Send + Sync> Sync for Weak<T> {}
+
+impl<T> Weak<T> {
+    /// Creates a `Weak` without an allocation, which never upgrades.
+    pub fn new() -> Self {
+        Weak {
+            // SAFETY: usize::MAX is not null.
+            inner: unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(usize::MAX)) },
+            _marker: PhantomData,
+        }
+    }
+
+    fn inner(&self) -> Option<&ArcInner<T>> {
+        if self.inner.as_ptr() as usize == usize::MAX {
+            None
+        } else {
+            // SAFETY: the allocation is only freed once all Weaks are gone.
+            // we have a Weak, therefore it has not been freed.
+            // the value might have been dropped, but we only touch the counts.
+            Some(unsafe { &*(self.inner.as_ptr() as *const ArcInner<T>) })
+        }
+    }
+
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let inner = self.inner()?;
+        let mut cur = inner.strong.load(Ordering::Relaxed);
+        loop {
+            // the value has already been dropped.
+            if cur == 0 {
+                return None;
+            }
+            if cur > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire synchronizes with the Release store in make_mut restoring the count.
+            match inner.strong.compare_exchange_weak(
+                cur,
+                cur + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(Arc {
+                        inner: self.inner,
+                        _marker: PhantomData,
+                    })
+                }
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner()
+            .map_or(0, |inner| inner.strong.load(Ordering::Acquire))
+    }
+
+    pub fn weak_count(&self) -> usize {
+        match self.inner() {
+            Some(inner) => {
+                let weak = inner.weak.load(Ordering::Acquire);
+                let strong = inner.strong.load(Ordering::Acquire);
+                if strong == 0 {
+                    weak
+                } else {
+                    // the Arcs still hold their shared weak reference.
+                    weak - 1
+                }
+            }
+            None => 0,
+        }
+    }
+}
+
+impl<T> Default for Weak<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = self.inner() {
+            // we already hold a weak reference, so the count can't be locked by get_mut.
+            let old = inner.weak.fetch_add(1, Ordering::Relaxed);
+            if old > MAX_REFCOUNT {
+                std::process::abort();
+            }
+        }
+        Weak {
+            inner: self.inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        let inner = match self.inner() {
+            Some(inner) => inner,
+            None => return,
+        };
+        if inner.weak.fetch_sub(1, Ordering::Release) == 1 {
+            atomic::fence(Ordering::Acquire);
+            // SAFETY: there are no Arcs (they hold a weak reference) and no other Weaks left,
+            // so nobody can observe the allocation anymore. the value has already been dropped,
+            // so we only free the memory, which was allocated by Box with this layout.
+            unsafe { dealloc(self.inner.as_ptr() as *mut u8, Layout::new::<ArcInner<T>>()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_across_threads() {
+        let arc = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let arc = Arc::clone(&arc);
+                std::thread::spawn(move || {
+                    arc.fetch_add(1, Ordering::Relaxed);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(arc.load(Ordering::Relaxed), 8);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn make_mut_detaches_weaks() {
+        let mut arc = Arc::new(5);
+        let weak = Arc::downgrade(&arc);
+        assert!(Arc::get_mut(&mut arc).is_none());
+        *Arc::make_mut(&mut arc) += 1;
+        assert_eq!(*arc, 6);
+        assert!(weak.upgrade().is_none());
+        assert!(Arc::get_mut(&mut arc).is_some());
+    }
+
+    #[test]
+    fn make_mut_clones_when_shared() {
+        let mut a = Arc::new(String::from("a"));
+        let b = Arc::clone(&a);
+        Arc::make_mut(&mut a).push('b');
+        assert_eq!(*a, "ab");
+        assert_eq!(*b, "a");
+    }
+
+    #[test]
+    fn try_unwrap() {
+        let a = Arc::new(String::from("a"));
+        let b = Arc::clone(&a);
+        let a = Arc::try_unwrap(a).unwrap_err();
+        drop(b);
+        assert_eq!(Arc::try_unwrap(a).ok().as_deref(), Some("a"));
+    }
+}
@@ -0,0 +1,445 @@
+use std::cell::UnsafeCell;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+use std::sync::atomic::{self, AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+/// A thread-safe [`Cell`](crate::cell::Cell).
+///
+/// If `T` has the size and alignment of a native atomic integer, every operation is a single
+/// atomic instruction on its bits. Otherwise the cell is protected by one of a fixed set of
+/// global seqlocks, picked by address.
+///
+/// The atomic operations need `T: NoUninit`, since they treat the value as plain bytes.
+/// [`compare_exchange`] compares those bytes on either path, rather than calling `Eq`.
+///
+/// [`compare_exchange`]: AtomicCell::compare_exchange
+#[repr(transparent)]
+pub struct AtomicCell<T> {
+    value: UnsafeCell<T>,
+}
+
+// the value is only ever accessed atomically (or under a lock), and can be moved out of
+// the cell by any thread, so T only has to be Send.
+unsafe impl<T: Send> Send for AtomicCell<T> {}
+unsafe impl<T: Send> Sync for AtomicCell<T> {}
+
+/// Types whose bytes are all initialized, so their bits can be read as integers.
+///
+/// # Safety
+///
+/// The type must not contain padding or any other uninitialized bytes. For example a
+/// `#[repr(C)]` struct of a `u8` and a `u16` has a padding byte, and must not implement this.
+pub unsafe trait NoUninit: Copy {}
+
+macro_rules! no_uninit {
+    ($($t:ty),*) => {
+        $(unsafe impl NoUninit for $t {})*
+    };
+}
+
+no_uninit!(u8, u16, u32, u64, u128, usize);
+no_uninit!(i8, i16, i32, i64, i128, isize);
+no_uninit!(f32, f64, bool, char, ());
+
+unsafe impl<T: ?Sized> NoUninit for *const T {}
+unsafe impl<T: ?Sized> NoUninit for *mut T {}
+unsafe impl<T: NoUninit, const N: usize> NoUninit for [T; N] {}
+
+// whether a T can be treated as an A.
+fn can_transmute<T, A>() -> bool {
+    mem::size_of::<T>() == mem::size_of::<A>() && mem::align_of::<T>() >= mem::align_of::<A>()
+}
+
+// runs `$native` with `$a` bound to the cell viewed as the matching native atomic, and `$int`
+// bound to its integer type. if there is none, runs `$fallback`.
+macro_rules! native {
+    ($t:ty, $ptr:expr, |$a:ident: $int:ident| $native:expr, $fallback:expr) => {
+        loop {
+            native!(@try $t, $ptr, AtomicU8, u8, $a, $int, $native);
+            native!(@try $t, $ptr, AtomicU16, u16, $a, $int, $native);
+            native!(@try $t, $ptr, AtomicU32, u32, $a, $int, $native);
+            native!(@try $t, $ptr, AtomicU64, u64, $a, $int, $native);
+            break $fallback;
+        }
+    };
+    (@try $t:ty, $ptr:expr, $atomic:ident, $ty:ty, $a:ident, $int:ident, $native:expr) => {
+        if can_transmute::<$t, $atomic>() {
+            #[allow(dead_code)]
+            type $int = $ty;
+            let $a = &*($ptr as *const $atomic);
+            break $native;
+        }
+    };
+}
+
+// runs `$body` with `$a` and `$int` bound to the widest atomic and integer type a T splits
+// into evenly, and `$n` bound to the number of chunks.
+macro_rules! chunks {
+    ($t:ty, |$a:ident: $int:ident, $n:ident| $body:expr) => {
+        if mem::align_of::<$t>() >= mem::align_of::<AtomicUsize>()
+            && mem::size_of::<$t>() % mem::size_of::<AtomicUsize>() == 0
+        {
+            type $a = AtomicUsize;
+            type $int = usize;
+            let $n = mem::size_of::<$t>() / mem::size_of::<AtomicUsize>();
+            $body
+        } else {
+            type $a = AtomicU8;
+            type $int = u8;
+            let $n = mem::size_of::<$t>();
+            $body
+        }
+    };
+}
+
+// copies the value out of `src` with relaxed atomic loads, so that it may race with
+// `atomic_store`. if it does, the result is torn and possibly not a valid T.
+unsafe fn atomic_load<T: NoUninit>(src: *const T) -> MaybeUninit<T> {
+    let mut value = MaybeUninit::<T>::uninit();
+    chunks!(T, |A: Int, n| {
+        let src = src as *const A;
+        let dst = value.as_mut_ptr() as *mut Int;
+        for i in 0..n {
+            dst.add(i).write((*src.add(i)).load(Ordering::Relaxed));
+        }
+    });
+    value
+}
+
+// whether a and b have the same bytes.
+fn bytes_eq<T: NoUninit>(a: &T, b: &T) -> bool {
+    let bytes = |v: &T| {
+        // SAFETY: T has no uninitialized bytes.
+        unsafe { std::slice::from_raw_parts(v as *const T as *const u8, mem::size_of::<T>()) }
+    };
+    bytes(a) == bytes(b)
+}
+
+// the writing side of `atomic_load`. writers must still exclude each other.
+unsafe fn atomic_store<T: NoUninit>(dst: *mut T, value: T) {
+    chunks!(T, |A: Int, n| {
+        let src = &value as *const T as *const Int;
+        let dst = dst as *const A;
+        for i in 0..n {
+            (*dst.add(i)).store(src.add(i).read(), Ordering::Relaxed);
+        }
+    });
+}
+
+impl<T> AtomicCell<T> {
+    pub const fn new(value: T) -> Self {
+        AtomicCell {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Whether operations on this cell use native atomics rather than a lock.
+    pub fn is_lock_free() -> bool {
+        can_transmute::<T, AtomicU8>()
+            || can_transmute::<T, AtomicU16>()
+            || can_transmute::<T, AtomicU32>()
+            || can_transmute::<T, AtomicU64>()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: NoUninit> AtomicCell<T> {
+    pub fn store(&self, value: T) {
+        self.swap(value);
+    }
+
+    pub fn swap(&self, value: T) -> T {
+        let dst = self.value.get();
+        // SAFETY: T has the size and alignment of the atomic, and no uninitialized bytes. the
+        // cell is only ever accessed atomically, or under the lock with atomic writes.
+        unsafe {
+            native! {
+                T,
+                dst,
+                |a: Int| {
+                    let new = mem::transmute_copy::<T, Int>(&value);
+                    mem::transmute_copy::<Int, T>(&a.swap(new, Ordering::SeqCst))
+                },
+                {
+                    let _guard = lock(dst as usize).write();
+                    let old = ptr::read(dst);
+                    atomic_store(dst, value);
+                    old
+                }
+            }
+        }
+    }
+
+    pub fn load(&self) -> T {
+        let src = self.value.get();
+        // SAFETY: see swap.
+        unsafe {
+            native! {
+                T,
+                src,
+                |a: Int| mem::transmute_copy::<Int, T>(&a.load(Ordering::SeqCst)),
+                {
+                    let lock = lock(src as usize);
+                    // try to read without taking the lock. the read may race with a write, in
+                    // which case it is torn, so we only look at it if no write happened meanwhile.
+                    if let Some(stamp) = lock.optimistic_read() {
+                        let value = atomic_load(src);
+                        if lock.validate_read(stamp) {
+                            return value.assume_init();
+                        }
+                    }
+                    let guard = lock.write();
+                    let value = ptr::read(src);
+                    // we didn't write anything, so readers don't have to retry.
+                    guard.abort();
+                    value
+                }
+            }
+        }
+    }
+
+    /// Stores `new` if the current value is `current`.
+    ///
+    /// Returns the previous value, as `Ok` if it was replaced and as `Err` otherwise.
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        let dst = self.value.get();
+        // SAFETY: see swap.
+        unsafe {
+            native! {
+                T,
+                dst,
+                |a: Int| {
+                    let current = mem::transmute_copy::<T, Int>(&current);
+                    let new = mem::transmute_copy::<T, Int>(&new);
+                    match a.compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst) {
+                        Ok(old) => Ok(mem::transmute_copy::<Int, T>(&old)),
+                        Err(old) => Err(mem::transmute_copy::<Int, T>(&old)),
+                    }
+                },
+                {
+                    let guard = lock(dst as usize).write();
+                    let old = ptr::read(dst);
+                    if bytes_eq(&old, &current) {
+                        atomic_store(dst, new);
+                        Ok(old)
+                    } else {
+                        guard.abort();
+                        Err(old)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Repeatedly applies `f` to the current value until the result can be stored without
+    /// interference, or `f` returns `None`.
+    ///
+    /// Returns the previous value, as `Ok` if it was updated and as `Err` otherwise.
+    pub fn fetch_update<F>(&self, mut f: F) -> Result<T, T>
+    where
+        F: FnMut(T) -> Option<T>,
+    {
+        let mut current = self.load();
+        while let Some(new) = f(current) {
+            match self.compare_exchange(current, new) {
+                Ok(old) => return Ok(old),
+                Err(old) => current = old,
+            }
+        }
+        Err(current)
+    }
+}
+
+impl<T: Default> Default for AtomicCell<T> {
+    fn default() -> Self {
+        AtomicCell::new(T::default())
+    }
+}
+
+/// A sequence lock: writers take it exclusively and bump the stamp,
+/// readers read optimistically and check the stamp didn't change.
+struct SeqLock {
+    // odd while a writer holds the lock.
+    stamp: AtomicUsize,
+}
+
+impl SeqLock {
+    const fn new() -> Self {
+        SeqLock {
+            stamp: AtomicUsize::new(0),
+        }
+    }
+
+    fn optimistic_read(&self) -> Option<usize> {
+        // Acquire pairs with the Release in SeqLockWriteGuard::drop,
+        // so we see the value written before the stamp.
+        let stamp = self.stamp.load(Ordering::Acquire);
+        if stamp & 1 == 0 {
+            Some(stamp)
+        } else {
+            None
+        }
+    }
+
+    fn validate_read(&self, stamp: usize) -> bool {
+        // keeps the read of the value from moving after the re-check of the stamp.
+        atomic::fence(Ordering::Acquire);
+        self.stamp.load(Ordering::Relaxed) == stamp
+    }
+
+    fn write(&self) -> SeqLockWriteGuard<'_> {
+        loop {
+            let stamp = self.stamp.load(Ordering::Relaxed);
+            if stamp & 1 == 0
+                && self
+                    .stamp
+                    .compare_exchange_weak(stamp, stamp + 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                // keeps our writes from moving before the odd stamp is visible.
+                atomic::fence(Ordering::Release);
+                return SeqLockWriteGuard { lock: self, stamp };
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+struct SeqLockWriteGuard<'a> {
+    lock: &'a SeqLock,
+    // the stamp before we took the lock.
+    stamp: usize,
+}
+
+impl SeqLockWriteGuard<'_> {
+    /// Releases the lock without invalidating optimistic reads, since nothing was written.
+    fn abort(self) {
+        self.lock.stamp.store(self.stamp, Ordering::Release);
+        mem::forget(self);
+    }
+}
+
+impl Drop for SeqLockWriteGuard<'_> {
+    fn drop(&mut self) {
+        // Release publishes our writes to everyone who reads the new stamp.
+        self.lock.stamp.store(self.stamp + 2, Ordering::Release);
+    }
+}
+
+// the number of locks the cells are striped across. prime, so that cells at addresses
+// with a common stride don't all end up on the same lock.
+const LOCKS: usize = 67;
+
+fn lock(addr: usize) -> &'static SeqLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: SeqLock = SeqLock::new();
+    static LOCK_STRIPES: [SeqLock; LOCKS] = [INIT; LOCKS];
+    &LOCK_STRIPES[addr % LOCKS]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::Arc;
+    use std::fmt;
+    use std::thread;
+
+    // the lost-update race from compile_fail.rs, which AtomicCell doesn't have.
+    #[test]
+    fn concurrent_get_set() {
+        let n = if cfg!(miri) { 100 } else { 1000000 };
+        let x = Arc::new(AtomicCell::new(0u64));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let x = Arc::clone(&x);
+                thread::spawn(move || {
+                    for _ in 0..n {
+                        x.fetch_update(|x| Some(x + 1)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(x.load(), 2 * n);
+    }
+
+    #[test]
+    fn concurrent_fallback() {
+        let n = if cfg!(miri) { 50 } else { 10000 };
+        assert!(!AtomicCell::<[u64; 3]>::is_lock_free());
+        let x = Arc::new(AtomicCell::new([0u64; 3]));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let x = Arc::clone(&x);
+                thread::spawn(move || {
+                    for _ in 0..n {
+                        x.fetch_update(|[a, b, c]| Some([a + 1, b + 2, c + 3]))
+                            .unwrap();
+                        let [a, b, c] = x.load();
+                        assert!(b == 2 * a && c == 3 * a, "torn read");
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(x.load(), [4 * n, 8 * n, 12 * n]);
+    }
+
+    // equal to everything, so only a bytewise comparison tells values apart.
+    #[derive(Clone, Copy, Debug)]
+    #[repr(transparent)]
+    struct AnyEq<A>(A);
+
+    impl<A> PartialEq for AnyEq<A> {
+        fn eq(&self, _: &Self) -> bool {
+            true
+        }
+    }
+
+    impl<A> Eq for AnyEq<A> {}
+
+    unsafe impl<A: NoUninit> NoUninit for AnyEq<A> {}
+
+    fn compare_exchange_ignores_eq<A: NoUninit + PartialEq + fmt::Debug>(a: A, b: A, c: A) {
+        let x = AtomicCell::new(AnyEq(a));
+        assert_eq!(x.compare_exchange(AnyEq(b), AnyEq(c)).unwrap_err().0, a);
+        assert_eq!(x.compare_exchange(AnyEq(a), AnyEq(c)).unwrap().0, a);
+        assert_eq!(x.load().0, c);
+    }
+
+    #[test]
+    fn compare_exchange_is_bytewise() {
+        assert!(AtomicCell::<AnyEq<u32>>::is_lock_free());
+        compare_exchange_ignores_eq(1u32, 2, 3);
+        assert!(!AtomicCell::<AnyEq<[u8; 3]>>::is_lock_free());
+        compare_exchange_ignores_eq([1u8; 3], [2; 3], [3; 3]);
+    }
+
+    #[test]
+    fn single_threaded_ops() {
+        let native = AtomicCell::new(5u32);
+        assert!(AtomicCell::<u32>::is_lock_free());
+        assert_eq!(native.swap(6), 5);
+        assert_eq!(native.compare_exchange(5, 7), Err(6));
+        assert_eq!(native.compare_exchange(6, 7), Ok(6));
+        assert_eq!(native.fetch_update(|_| None), Err(7));
+
+        let locked = AtomicCell::new([1u8; 3]);
+        assert!(!AtomicCell::<[u8; 3]>::is_lock_free());
+        assert_eq!(locked.swap([2; 3]), [1; 3]);
+        assert_eq!(locked.compare_exchange([1; 3], [3; 3]), Err([2; 3]));
+        locked.store([4; 3]);
+        assert_eq!(locked.into_inner(), [4; 3]);
+    }
+}
@@ -0,0 +1,111 @@
+use super::mutex::MutexGuard;
+use super::parking::WaitQueue;
+use super::poison::LockResult;
+
+/// A condition variable, to wait for some condition on the data behind a [`Mutex`].
+///
+/// [`Mutex`]: super::Mutex
+pub struct Condvar {
+    waiters: WaitQueue,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Condvar {
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Unlocks the mutex and blocks until notified, then locks the mutex again.
+    ///
+    /// Notifications from before the call are missed, so the condition should be checked
+    /// in a loop (see [`Condvar::wait_while`]).
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> LockResult<MutexGuard<'a, T>> {
+        let mutex = MutexGuard::mutex(&guard);
+        // enqueue before unlocking, so a notification sent right after we unlock isn't lost.
+        let ticket = self.waiters.enqueue();
+        drop(guard);
+        ticket.wait();
+        mutex.lock()
+    }
+
+    /// Blocks until `condition` returns false.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> LockResult<MutexGuard<'a, T>>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard)?;
+        }
+        Ok(guard)
+    }
+
+    pub fn notify_one(&self) {
+        self.waiters.wake_one();
+    }
+
+    pub fn notify_all(&self) {
+        self.waiters.wake_all();
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Condvar::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Mutex;
+    use super::*;
+    use crate::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn producer_consumer() {
+        let pair = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
+        let producer = {
+            let pair = Arc::clone(&pair);
+            thread::spawn(move || {
+                for i in 0..10 {
+                    pair.0.lock().unwrap().push(i);
+                    pair.1.notify_one();
+                }
+            })
+        };
+
+        let mut received = Vec::new();
+        while received.len() < 10 {
+            let mut queue = pair
+                .1
+                .wait_while(pair.0.lock().unwrap(), |q| q.is_empty())
+                .unwrap();
+            received.append(&mut queue);
+        }
+        producer.join().unwrap();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn notify_all_wakes_everyone() {
+        let pair = Arc::new((Mutex::new(false), Condvar::new()));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let pair = Arc::clone(&pair);
+                thread::spawn(move || {
+                    let _ready = pair.1.wait_while(pair.0.lock().unwrap(), |ready| !*ready);
+                })
+            })
+            .collect();
+        *pair.0.lock().unwrap() = true;
+        pair.1.notify_all();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+}
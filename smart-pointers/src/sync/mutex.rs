@@ -0,0 +1,217 @@
+use super::parking::{WaitQueue, SPIN_LIMIT};
+use super::poison::{self, LockResult, TryLockError, TryLockResult};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A mutual exclusion lock that spins for a bit, then parks the waiting thread.
+///
+/// Like the std `Mutex`, it is poisoned if a thread panics while holding it.
+pub struct Mutex<T: ?Sized> {
+    locked: AtomicBool,
+    waiters: WaitQueue,
+    poison: poison::Flag,
+    value: UnsafeCell<T>,
+}
+
+// the lock makes sure only one thread at a time accesses the value,
+// so we only need to be able to send the value between threads.
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Mutex {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            poison: poison::Flag::new(),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.poison.get();
+        let value = self.value.into_inner();
+        if poisoned {
+            Err(poison::PoisonError::new(value))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    fn try_acquire(&self) -> bool {
+        // Acquire pairs with the Release in unlock, so we see everything the last holder did.
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Blocks until the lock is acquired.
+    ///
+    /// Locking a mutex the current thread already holds deadlocks.
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        for _ in 0..SPIN_LIMIT {
+            if self.try_acquire() {
+                return self.guard();
+            }
+            std::hint::spin_loop();
+        }
+
+        loop {
+            if self.try_acquire() {
+                return self.guard();
+            }
+            let ticket = self.waiters.enqueue();
+            // the lock might have been released before we enqueued, and nobody would wake us.
+            if self.try_acquire() {
+                self.waiters.cancel(ticket);
+                return self.guard();
+            }
+            ticket.wait();
+        }
+    }
+
+    pub fn try_lock(&self) -> TryLockResult<MutexGuard<'_, T>> {
+        if self.try_acquire() {
+            Ok(self.guard()?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+
+    // must only be called while holding the lock.
+    fn guard(&self) -> LockResult<MutexGuard<'_, T>> {
+        poison::map_result(self.poison.guard(), |poison| MutexGuard {
+            mutex: self,
+            poison,
+            _not_send: PhantomData,
+        })
+    }
+
+    fn unlock(&self) {
+        // Release pairs with the Acquire in try_acquire.
+        self.locked.store(false, Ordering::Release);
+        self.waiters.wake_one();
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let poisoned = self.poison.get();
+        let value = self.value.get_mut();
+        if poisoned {
+            Err(poison::PoisonError::new(value))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Mutex::new(T::default())
+    }
+}
+
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    poison: poison::Guard,
+    // the lock has to be released on the thread that took it, like the std MutexGuard.
+    _not_send: PhantomData<*const ()>,
+}
+
+// sharing a guard only hands out &T.
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    pub(crate) fn mutex(this: &Self) -> &'a Mutex<T> {
+        this.mutex
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: a MutexGuard only exists while the lock is held.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: a MutexGuard only exists while the lock is held, and we have the only one.
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.poison.done(&self.poison);
+        self.mutex.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn counter() {
+        let counter = Arc::new(Mutex::new(0));
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let counter = Arc::clone(&counter);
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        *counter.lock().unwrap() += 1;
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*counter.lock().unwrap(), 1000);
+    }
+
+    #[test]
+    fn try_lock() {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+        assert!(matches!(mutex.try_lock(), Err(TryLockError::WouldBlock)));
+        drop(guard);
+        assert!(mutex.try_lock().is_ok());
+    }
+
+    #[test]
+    fn poisoned_by_panic() {
+        let mutex = Arc::new(Mutex::new(1));
+        let m = Arc::clone(&mutex);
+        let result = thread::spawn(move || {
+            let mut guard = m.lock().unwrap();
+            *guard = 2;
+            panic!("while holding the lock");
+        })
+        .join();
+        assert!(result.is_err());
+
+        assert!(mutex.is_poisoned());
+        let guard = mutex.lock().err().unwrap().into_inner();
+        assert_eq!(*guard, 2);
+        drop(guard);
+
+        mutex.clear_poison();
+        assert_eq!(*mutex.lock().unwrap(), 2);
+    }
+}
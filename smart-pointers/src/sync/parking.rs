@@ -0,0 +1,114 @@
+use crate::sync::Arc;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, Thread};
+
+// how often a lock is retried before its thread is parked.
+pub(crate) const SPIN_LIMIT: usize = 100;
+
+struct Waiter {
+    id: u64,
+    thread: Thread,
+    notified: Arc<AtomicBool>,
+}
+
+/// A queue of parked threads, waiting for some condition.
+///
+/// To wait without missing a wakeup, a thread first enqueues itself, then re-checks
+/// the condition, and only then waits on its ticket (or cancels it if the condition holds).
+pub(crate) struct WaitQueue {
+    // a spin lock around the waiters. it is only held for a few instructions at a time.
+    locked: AtomicBool,
+    waiters: UnsafeCell<VecDeque<Waiter>>,
+    next_id: AtomicU64,
+}
+
+// SAFETY: the waiters are only accessed while holding the spin lock.
+unsafe impl Send for WaitQueue {}
+unsafe impl Sync for WaitQueue {}
+
+impl WaitQueue {
+    pub(crate) fn new() -> Self {
+        WaitQueue {
+            locked: AtomicBool::new(false),
+            waiters: UnsafeCell::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut VecDeque<Waiter>) -> R) -> R {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        // SAFETY: we hold the spin lock, so we have exclusive access to the waiters.
+        // f doesn't run user code, so it can't panic and leave the lock held.
+        let r = f(unsafe { &mut *self.waiters.get() });
+        self.locked.store(false, Ordering::Release);
+        r
+    }
+
+    /// Enqueues the current thread.
+    pub(crate) fn enqueue(&self) -> Ticket {
+        let notified = Arc::new(AtomicBool::new(false));
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let waiter = Waiter {
+            id,
+            thread: thread::current(),
+            notified: Arc::clone(&notified),
+        };
+        self.with(|waiters| waiters.push_back(waiter));
+        Ticket { id, notified }
+    }
+
+    /// Removes a ticket that is no longer going to wait, so it doesn't swallow a wakeup.
+    /// If it has been woken in the meantime, the wakeup is passed on to the next waiter.
+    pub(crate) fn cancel(&self, ticket: Ticket) {
+        let removed = self.with(|waiters| {
+            let pos = waiters.iter().position(|w| w.id == ticket.id);
+            pos.and_then(|pos| waiters.remove(pos)).is_some()
+        });
+        if !removed {
+            self.wake_one();
+        }
+    }
+
+    pub(crate) fn wake_one(&self) {
+        if let Some(waiter) = self.with(VecDeque::pop_front) {
+            waiter.wake();
+        }
+    }
+
+    pub(crate) fn wake_all(&self) {
+        for waiter in self.with(std::mem::take) {
+            waiter.wake();
+        }
+    }
+}
+
+impl Waiter {
+    fn wake(self) {
+        // Release pairs with the Acquire in Ticket::wait.
+        self.notified.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+pub(crate) struct Ticket {
+    id: u64,
+    notified: Arc<AtomicBool>,
+}
+
+impl Ticket {
+    /// Parks the current thread until the ticket is woken.
+    pub(crate) fn wait(self) {
+        // park can wake up spuriously, so we check that we've actually been notified.
+        while !self.notified.load(Ordering::Acquire) {
+            thread::park();
+        }
+    }
+}
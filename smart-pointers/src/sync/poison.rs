@@ -0,0 +1,136 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+/// Returned by a lock whose previous holder panicked, which may have left the data in an
+/// inconsistent state. The guard is still available through [`PoisonError::into_inner`].
+pub struct PoisonError<T> {
+    guard: T,
+}
+
+impl<T> PoisonError<T> {
+    pub fn new(guard: T) -> Self {
+        PoisonError { guard }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.guard
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.guard
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> fmt::Debug for PoisonError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for PoisonError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "poisoned lock: another thread panicked while holding it")
+    }
+}
+
+impl<T> Error for PoisonError<T> {}
+
+/// Returned by the `try_*` methods of locks.
+pub enum TryLockError<T> {
+    Poisoned(PoisonError<T>),
+    /// The lock is held by someone else.
+    WouldBlock,
+}
+
+impl<T> From<PoisonError<T>> for TryLockError<T> {
+    fn from(err: PoisonError<T>) -> Self {
+        TryLockError::Poisoned(err)
+    }
+}
+
+impl<T> fmt::Debug for TryLockError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => err.fmt(f),
+            TryLockError::WouldBlock => write!(f, "WouldBlock"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TryLockError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(err) => err.fmt(f),
+            TryLockError::WouldBlock => write!(f, "try_lock failed because the lock is held"),
+        }
+    }
+}
+
+impl<T> Error for TryLockError<T> {}
+
+pub type LockResult<G> = Result<G, PoisonError<G>>;
+pub type TryLockResult<G> = Result<G, TryLockError<G>>;
+
+/// Whether a lock has been poisoned.
+pub(crate) struct Flag {
+    failed: AtomicBool,
+}
+
+/// Remembers whether the thread was already panicking when it took a lock,
+/// so only a panic that starts while holding the lock poisons it.
+pub(crate) struct Guard {
+    panicking: bool,
+}
+
+impl Flag {
+    pub(crate) fn new() -> Self {
+        Flag {
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Called after taking the lock. Wraps the lock guard in an error if we're poisoned.
+    pub(crate) fn guard(&self) -> Result<Guard, PoisonError<Guard>> {
+        let guard = Guard {
+            panicking: thread::panicking(),
+        };
+        if self.get() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Called before releasing the lock.
+    pub(crate) fn done(&self, guard: &Guard) {
+        if !guard.panicking && thread::panicking() {
+            // Relaxed is enough, the release of the lock publishes it.
+            self.failed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn get(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.failed.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Turns the poison state into a lock result around the actual lock guard.
+pub(crate) fn map_result<T, U>(
+    result: Result<T, PoisonError<T>>,
+    f: impl FnOnce(T) -> U,
+) -> LockResult<U> {
+    match result {
+        Ok(t) => Ok(f(t)),
+        Err(err) => Err(PoisonError::new(f(err.into_inner()))),
+    }
+}
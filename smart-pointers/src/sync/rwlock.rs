@@ -0,0 +1,326 @@
+use super::parking::{WaitQueue, SPIN_LIMIT};
+use super::poison::{self, LockResult, TryLockError, TryLockResult};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// the state of a write-locked RwLock. any other state is the number of readers.
+const WRITE_LOCKED: usize = usize::MAX;
+
+/// A reader-writer lock: any number of readers, or a single writer.
+///
+/// Waiting writers block new readers, so writers can't be starved.
+/// Like the std `RwLock`, it is poisoned if a writer panics while holding it.
+pub struct RwLock<T: ?Sized> {
+    state: AtomicUsize,
+    // the number of writers waiting in the slow path.
+    writers_waiting: AtomicUsize,
+    readers: WaitQueue,
+    writers: WaitQueue,
+    poison: poison::Flag,
+    value: UnsafeCell<T>,
+}
+
+// readers on different threads share &T, so T must be Sync as well.
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> Self {
+        RwLock {
+            state: AtomicUsize::new(0),
+            writers_waiting: AtomicUsize::new(0),
+            readers: WaitQueue::new(),
+            writers: WaitQueue::new(),
+            poison: poison::Flag::new(),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> LockResult<T> {
+        let poisoned = self.poison.get();
+        let value = self.value.into_inner();
+        if poisoned {
+            Err(poison::PoisonError::new(value))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    fn try_acquire_read(&self) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state == WRITE_LOCKED || self.writers_waiting.load(Ordering::Relaxed) > 0 {
+                return false;
+            }
+            // Acquire pairs with the Release in write_unlock.
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    fn try_acquire_write(&self) -> bool {
+        // Acquire pairs with the Release in read_unlock and write_unlock.
+        self.state
+            .compare_exchange(0, WRITE_LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Blocks until the lock is acquired for reading.
+    ///
+    /// Read-locking a lock the current thread already holds may deadlock,
+    /// if a writer starts waiting in between.
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+        for _ in 0..SPIN_LIMIT {
+            if self.try_acquire_read() {
+                return self.read_guard();
+            }
+            std::hint::spin_loop();
+        }
+
+        loop {
+            if self.try_acquire_read() {
+                return self.read_guard();
+            }
+            let ticket = self.readers.enqueue();
+            // the lock might have been released before we enqueued, and nobody would wake us.
+            if self.try_acquire_read() {
+                self.readers.cancel(ticket);
+                return self.read_guard();
+            }
+            ticket.wait();
+        }
+    }
+
+    /// Blocks until the lock is acquired for writing.
+    pub fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+        for _ in 0..SPIN_LIMIT {
+            if self.try_acquire_write() {
+                return self.write_guard();
+            }
+            std::hint::spin_loop();
+        }
+
+        self.writers_waiting.fetch_add(1, Ordering::Relaxed);
+        loop {
+            if self.try_acquire_write() {
+                break;
+            }
+            let ticket = self.writers.enqueue();
+            // the lock might have been released before we enqueued, and nobody would wake us.
+            if self.try_acquire_write() {
+                self.writers.cancel(ticket);
+                break;
+            }
+            ticket.wait();
+        }
+        self.writers_waiting.fetch_sub(1, Ordering::Relaxed);
+        self.write_guard()
+    }
+
+    pub fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, T>> {
+        if self.try_acquire_read() {
+            Ok(self.read_guard()?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+
+    pub fn try_write(&self) -> TryLockResult<RwLockWriteGuard<'_, T>> {
+        if self.try_acquire_write() {
+            Ok(self.write_guard()?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+
+    // must only be called while holding a read lock.
+    fn read_guard(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+        poison::map_result(self.poison.guard(), |_| RwLockReadGuard {
+            lock: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    // must only be called while holding the write lock.
+    fn write_guard(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+        poison::map_result(self.poison.guard(), |poison| RwLockWriteGuard {
+            lock: self,
+            poison,
+            _not_send: PhantomData,
+        })
+    }
+
+    fn read_unlock(&self) {
+        // Release pairs with the Acquire in try_acquire_write.
+        if self.state.fetch_sub(1, Ordering::Release) == 1 {
+            // we were the last reader, so only writers can be waiting for us.
+            self.writers.wake_one();
+        }
+    }
+
+    fn write_unlock(&self) {
+        // Release pairs with the Acquire in try_acquire_read and try_acquire_write.
+        self.state.store(0, Ordering::Release);
+        // let everyone compete for the lock. waiting writers keep new readers out,
+        // and the readers that lose go back to sleep.
+        self.writers.wake_one();
+        self.readers.wake_all();
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+
+    pub fn clear_poison(&self) {
+        self.poison.clear();
+    }
+
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let poisoned = self.poison.get();
+        let value = self.value.get_mut();
+        if poisoned {
+            Err(poison::PoisonError::new(value))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        RwLock::new(T::default())
+    }
+}
+
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: a read guard only exists while the lock is held for reading,
+        // so nobody can have a mutable reference.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        // readers can't have modified the value, so a panic doesn't poison the lock.
+        self.lock.read_unlock();
+    }
+}
+
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    poison: poison::Guard,
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: a write guard only exists while the lock is held for writing.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: a write guard only exists while the lock is held for writing,
+        // and there is only one of it.
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.poison.done(&self.poison);
+        self.lock.write_unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn readers_and_writers() {
+        let lock = Arc::new(RwLock::new(Vec::new()));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let lock = Arc::clone(&lock);
+                thread::spawn(move || {
+                    for _ in 0..20 {
+                        if i % 2 == 0 {
+                            lock.write().unwrap().push(i);
+                        } else {
+                            let v = lock.read().unwrap();
+                            assert!(v.iter().all(|x| x % 2 == 0));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(lock.read().unwrap().len(), 80);
+    }
+
+    #[test]
+    fn shared_reads_exclusive_write() {
+        let lock = RwLock::new(5);
+        let r1 = lock.read().unwrap();
+        let r2 = lock.try_read().unwrap();
+        assert_eq!(*r1 + *r2, 10);
+        assert!(matches!(lock.try_write(), Err(TryLockError::WouldBlock)));
+        drop((r1, r2));
+
+        let mut w = lock.try_write().unwrap();
+        *w += 1;
+        assert!(matches!(lock.try_read(), Err(TryLockError::WouldBlock)));
+        drop(w);
+        assert_eq!(lock.into_inner().unwrap(), 6);
+    }
+
+    #[test]
+    fn poisoned_by_writer_panic() {
+        let lock = Arc::new(RwLock::new(0));
+        let l = Arc::clone(&lock);
+        let _ = thread::spawn(move || {
+            let _r = l.read().unwrap();
+            panic!("while reading");
+        })
+        .join();
+        assert!(!lock.is_poisoned());
+
+        let l = Arc::clone(&lock);
+        let _ = thread::spawn(move || {
+            let _w = l.write().unwrap();
+            panic!("while writing");
+        })
+        .join();
+        assert!(lock.is_poisoned());
+        assert!(lock.read().is_err());
+    }
+}